@@ -43,7 +43,7 @@ fn index_item_decode(c: &mut Criterion) {
     c.bench_function("bin_search", |b| {
         let set = make_set();
         b.iter(|| {
-            let _ = set.has_bin_search(black_box(333 as u32));
+            let _ = set.has_bin_search(black_box(333_u32));
         });
     });
 
@@ -51,9 +51,23 @@ fn index_item_decode(c: &mut Criterion) {
         let mut set = make_set();
         set.update_index(50.0);
         b.iter(|| {
-            let _ = set.has_bin_search(black_box(333 as u32));
+            let _ = set.has_bin_search(black_box(333_u32));
         });
     });
+
+    c.bench_function("decode_into", |b| {
+        let set = make_set();
+        let mut out = Vec::with_capacity(set.len());
+        b.iter(|| {
+            out.clear();
+            set.decode_into(black_box(&mut out));
+        });
+    });
+
+    c.bench_function("iter_collect", |b| {
+        let set = make_set();
+        b.iter(|| black_box(&set).iter().collect::<Vec<_>>());
+    });
 }
 
 criterion_group!(benches, index_item_decode);
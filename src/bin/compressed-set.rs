@@ -0,0 +1,245 @@
+use compressed_set::{utils, CompressedSequence};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    process::ExitCode,
+    str::FromStr,
+};
+
+const USAGE: &str = "\
+Inspect and convert lists of u32 numbers and compressed sets
+
+Usage: compressed-set <COMMAND> [OPTIONS] [INPUT]
+
+Commands:
+  stats    Print statistics and the step size histogram of a list or set
+  convert  Convert a list or set into another format
+
+Arguments:
+  [INPUT]  Input file, stdin if omitted or '-'
+
+Options:
+  -s, --step <STEP>      Step size of the set, picked automatically if omitted
+  -f, --from <FORMAT>    Input format [default: text]
+  -t, --to <FORMAT>      Output format of convert [default: set]
+  -o, --output <FILE>    Output file of convert, stdout if omitted
+  -h, --help             Print this help
+
+Formats:
+  text    Newline delimited numbers
+  binary  Little endian u32 numbers
+  set     Serialized compressed set
+  json    Compressed set as JSON";
+
+/// Amount of step sizes shown in the histogram
+const HISTOGRAM_STEPS: usize = 10;
+
+/// Width of the largest bar in the histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Stats,
+    Convert,
+}
+
+/// Format of input and output data
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Binary,
+    Set,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "text" => Self::Text,
+            "binary" => Self::Binary,
+            "set" => Self::Set,
+            "json" => Self::Json,
+            _ => return Err(format!("unknown format '{s}'")),
+        })
+    }
+}
+
+struct Args {
+    command: Command,
+    step: Option<u32>,
+    from: Format,
+    to: Format,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+impl Args {
+    /// Parses the command line arguments. Returns `None` if the help should be printed
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let command = match args.next().as_deref() {
+            Some("stats") => Command::Stats,
+            Some("convert") => Command::Convert,
+            Some("-h" | "--help") | None => return Ok(None),
+            Some(cmd) => return Err(format!("unknown command '{cmd}'").into()),
+        };
+
+        let mut parsed = Self {
+            command,
+            step: None,
+            from: Format::Text,
+            to: Format::Set,
+            input: None,
+            output: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for '{arg}'"));
+            match arg.as_str() {
+                "-s" | "--step" => parsed.step = Some(value()?.parse()?),
+                "-f" | "--from" => parsed.from = value()?.parse()?,
+                "-t" | "--to" => parsed.to = value()?.parse()?,
+                "-o" | "--output" => parsed.output = Some(value()?),
+                "-h" | "--help" => return Ok(None),
+                _ if parsed.input.is_none() => parsed.input = Some(arg),
+                _ => return Err(format!("unexpected argument '{arg}'").into()),
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<()> {
+    let set = read_set(args)?;
+
+    match args.command {
+        Command::Stats => print_stats(&set),
+        Command::Convert => match &args.output {
+            Some(path) => write_set(&set, args.to, BufWriter::new(File::create(path)?)),
+            None => write_set(&set, args.to, io::stdout().lock()),
+        },
+    }
+}
+
+/// Reads the input and builds a set with the requested step size
+fn read_set(args: &Args) -> Result<CompressedSequence> {
+    let mut data = vec![];
+    match args.input.as_deref() {
+        Some("-") | None => io::stdin().lock().read_to_end(&mut data)?,
+        Some(path) => File::open(path)?.read_to_end(&mut data)?,
+    };
+
+    let mut values = match args.from {
+        Format::Text => parse_text(&data)?,
+        Format::Binary => parse_binary(&data)?,
+        Format::Set => return Ok(with_step(bincode::deserialize(&data)?, args.step)),
+        Format::Json => return Ok(with_step(serde_json::from_slice(&data)?, args.step)),
+    };
+
+    values.sort_unstable();
+    values.dedup();
+    let step = args.step.unwrap_or_else(|| utils::max_step_size(&values));
+
+    let mut set = CompressedSequence::new(step);
+    set.extend(values);
+    Ok(set)
+}
+
+/// Re-encodes the set if a different step size was requested
+fn with_step(set: CompressedSequence, step: Option<u32>) -> CompressedSequence {
+    match step {
+        Some(step) if step != set.step() => CompressedSequence::from_iterator(step, set),
+        _ => set,
+    }
+}
+
+fn parse_text(data: &[u8]) -> Result<Vec<u32>> {
+    std::str::from_utf8(data)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse()
+                .map_err(|err| format!("invalid number '{line}': {err}").into())
+        })
+        .collect()
+}
+
+fn parse_binary(data: &[u8]) -> Result<Vec<u32>> {
+    if !data.len().is_multiple_of(4) {
+        return Err("binary input length is not a multiple of 4".into());
+    }
+
+    Ok(data
+        .chunks_exact(4)
+        .map(|i| u32::from_le_bytes(i.try_into().unwrap()))
+        .collect())
+}
+
+fn write_set<W: Write>(set: &CompressedSequence, format: Format, mut out: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            for value in set.iter() {
+                writeln!(out, "{value}")?;
+            }
+        }
+        Format::Binary => {
+            for value in set.iter() {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Format::Set => bincode::serialize_into(&mut out, set)?,
+        Format::Json => serde_json::to_writer(&mut out, set)?,
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn print_stats(set: &CompressedSequence) -> Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "{set:#?}")?;
+
+    let values = set.to_vec();
+    let mut steps: Vec<_> = utils::get_steps_freq(&values).into_iter().collect();
+    steps.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let total: u32 = steps.iter().map(|i| i.1).sum();
+    let max = steps.first().map(|i| i.1).unwrap_or(1);
+
+    writeln!(out, "\nStep sizes ({} distinct):", steps.len())?;
+    for (step, freq) in steps.iter().take(HISTOGRAM_STEPS) {
+        let bar = "#".repeat((*freq as usize * HISTOGRAM_WIDTH).div_ceil(max as usize));
+        let share = *freq as f64 / total as f64 * 100.0;
+        writeln!(out, "{step:>10} {freq:>10} {share:>6.2}% {bar}")?;
+    }
+
+    Ok(())
+}
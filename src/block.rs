@@ -0,0 +1,330 @@
+use crate::{codec, item::Item, CompressedSequence};
+use alloc::{vec, vec::Vec};
+use core::mem::size_of;
+
+/// Maximum amount of values within a single block
+pub const BLOCK_SIZE: usize = 128;
+
+/// Alternative layout of a set which groups the items into blocks of up to [`BLOCK_SIZE`]
+/// values. Each block is encoded independently and has a header with its first value and
+/// position, so positional and value lookups are a binary search over the headers followed
+/// by decoding a single block
+#[derive(Debug, Clone)]
+pub struct BlockSequence {
+    step: u32,
+    blocks: Vec<BlockHeader>,
+    data: Vec<u8>,
+    len: usize,
+}
+
+/// Header of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockHeader {
+    /// First value of the block
+    value: u32,
+    /// Position of the first value
+    position: u32,
+    /// Byte offset of the first item within the data
+    offset: u32,
+}
+
+impl BlockSequence {
+    /// Creates a new block layout containing the values of `set`. Sequence items get split if
+    /// they don't fit into a block
+    pub fn from_sequence(set: &CompressedSequence) -> Self {
+        let step = set.step;
+        let mut out = Self {
+            step,
+            blocks: vec![],
+            data: vec![],
+            len: 0,
+        };
+
+        let mut fill = BLOCK_SIZE;
+        let mut last = None;
+        for item in set.seq.iter() {
+            let mut next = Some(*item);
+            while let Some(mut item) = next.take() {
+                if fill == BLOCK_SIZE {
+                    // The first item of a block isn't encoded relative to the previous item so
+                    // each block can be decoded on its own
+                    last = None;
+                    out.blocks.push(BlockHeader {
+                        value: item.first_number(),
+                        position: out.len as u32,
+                        offset: out.data.len() as u32,
+                    });
+                    fill = 0;
+                }
+
+                let free = BLOCK_SIZE - fill;
+                if item.len() > free {
+                    let (left, right) = item.split(free, step);
+                    item = left;
+                    next = Some(right);
+                }
+
+                codec::encode_item(&mut out.data, &item, last);
+                last = Some(item.last_number(step));
+                fill += item.len();
+                out.len += item.len();
+            }
+        }
+
+        out.data.shrink_to_fit();
+        out.blocks.shrink_to_fit();
+        out
+    }
+
+    /// Creates a new block layout from the given values
+    #[inline]
+    pub fn from_iterator<I>(step: u32, iter: I) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        Self::from_sequence(&CompressedSequence::from_iterator(step, iter))
+    }
+
+    /// Converts the blocks back into a [`CompressedSequence`]
+    pub fn to_sequence(&self) -> CompressedSequence {
+        let mut set = CompressedSequence::new(self.step);
+        set.seq = (0..self.blocks.len()).flat_map(|b| self.items(b)).collect();
+        set
+    }
+
+    /// Returns the value at position `pos`
+    pub fn get(&self, pos: usize) -> Option<u32> {
+        if pos >= self.len {
+            return None;
+        }
+
+        let block = self.blocks.partition_point(|b| b.position as usize <= pos) - 1;
+        let mut pos = pos - self.blocks[block].position as usize;
+        for item in self.items(block) {
+            if pos < item.len() {
+                return item.at(pos, self.step);
+            }
+            pos -= item.len();
+        }
+
+        None
+    }
+
+    /// Returns the amount of values smaller than `value`
+    pub fn rank(&self, value: u32) -> usize {
+        let block = self.blocks.partition_point(|b| b.value < value);
+        if block == 0 {
+            return 0;
+        }
+
+        let mut rank = self.blocks[block - 1].position as usize;
+        for item in self.items(block - 1) {
+            let smaller = item.lower_bound(value, self.step);
+            rank += smaller;
+            if smaller < item.len() {
+                break;
+            }
+        }
+
+        rank
+    }
+
+    /// Returns `true` if the set contains `value`
+    pub fn contains(&self, value: u32) -> bool {
+        let block = self.blocks.partition_point(|b| b.value <= value);
+        if block == 0 {
+            return false;
+        }
+
+        self.items(block - 1)
+            .find(|i| i.last_number(self.step) >= value)
+            .is_some_and(|i| i.contains(value, self.step))
+    }
+
+    /// Returns an iterator over all values
+    #[inline]
+    pub fn iter(&self) -> BlockIter<'_> {
+        BlockIter {
+            set: self,
+            block: 0,
+            items: BlockItems::default(),
+            item: None,
+            ipos: 0,
+        }
+    }
+
+    /// Returns an iterator over the values of a single block
+    ///
+    /// # Panics
+    /// panics if `block` is not smaller than [`block_count`](BlockSequence::block_count)
+    pub fn iter_block(&self, block: usize) -> impl Iterator<Item = u32> + '_ {
+        self.items(block).flat_map(|i| i.iter(self.step))
+    }
+
+    /// Returns the amount of blocks
+    #[inline]
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the amount of values in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the step size of the set
+    #[inline]
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    /// Returns the amount of bytes used by the set
+    pub fn size_of(&self) -> usize {
+        size_of::<Self>() + self.blocks.capacity() * size_of::<BlockHeader>() + self.data.capacity()
+    }
+
+    /// Returns an iterator decoding the items of a block
+    pub(crate) fn items(&self, block: usize) -> BlockItems<'_> {
+        let start = self.blocks[block].offset as usize;
+        let end = self
+            .blocks
+            .get(block + 1)
+            .map_or(self.data.len(), |b| b.offset as usize);
+
+        BlockItems {
+            data: &self.data[start..end],
+            last: None,
+            step: self.step,
+        }
+    }
+}
+
+impl From<&CompressedSequence> for BlockSequence {
+    #[inline]
+    fn from(set: &CompressedSequence) -> Self {
+        Self::from_sequence(set)
+    }
+}
+
+/// Decodes the items of a single block
+#[derive(Default)]
+pub(crate) struct BlockItems<'a> {
+    data: &'a [u8],
+    last: Option<u32>,
+    step: u32,
+}
+
+impl<'a> Iterator for BlockItems<'a> {
+    type Item = Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        // Blocks are only created from valid items
+        let item = codec::decode_item(&mut self.data, self.last, self.step).expect("Invalid block");
+        self.last = Some(item.last_number(self.step));
+        Some(item)
+    }
+}
+
+/// Iterator over all values of a [`BlockSequence`]
+pub struct BlockIter<'a> {
+    set: &'a BlockSequence,
+    /// Next block to decode
+    block: usize,
+    items: BlockItems<'a>,
+    item: Option<Item>,
+    ipos: usize,
+}
+
+impl<'a> Iterator for BlockIter<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.item.and_then(|i| i.at(self.ipos, self.set.step)) {
+                self.ipos += 1;
+                return Some(val);
+            }
+
+            self.ipos = 0;
+            self.item = self.items.next();
+            if self.item.is_none() {
+                if self.block >= self.set.blocks.len() {
+                    return None;
+                }
+                self.items = self.set.items(self.block);
+                self.block += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sample_values;
+
+    #[test]
+    fn test_blocks() {
+        let values = sample_values();
+        for step in [1, 3, 10] {
+            let set = CompressedSequence::from_iterator(step, values.iter().copied());
+            let blocks = BlockSequence::from_sequence(&set);
+            assert_eq!(blocks.len(), values.len());
+            assert_eq!(blocks.iter().collect::<Vec<_>>(), values);
+            assert_eq!(blocks.to_sequence().to_vec(), values);
+
+            // Blocks are full except for the last one and can be decoded on their own
+            let mut pos = 0;
+            for block in 0..blocks.block_count() {
+                let block_values: Vec<u32> = blocks.iter_block(block).collect();
+                if block + 1 < blocks.block_count() {
+                    assert_eq!(block_values.len(), BLOCK_SIZE);
+                }
+                assert_eq!(block_values, values[pos..pos + block_values.len()]);
+                pos += block_values.len();
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocks_lookup() {
+        let values = sample_values();
+        let blocks = BlockSequence::from_iterator(10, values.iter().copied());
+
+        for (pos, value) in values.iter().enumerate() {
+            assert_eq!(blocks.get(pos), Some(*value));
+            assert_eq!(blocks.rank(*value), pos);
+            assert!(blocks.contains(*value));
+        }
+        assert_eq!(blocks.get(values.len()), None);
+
+        for value in [1, 9, 9_999, 10_002, 10_007, 300_001, 400_001, u32::MAX - 1] {
+            assert!(!blocks.contains(value));
+            assert_eq!(blocks.rank(value), values.partition_point(|v| *v < value));
+        }
+    }
+
+    #[test]
+    fn test_blocks_empty() {
+        let blocks = BlockSequence::from_sequence(&CompressedSequence::new(5));
+        assert!(blocks.is_empty());
+        assert_eq!(blocks.block_count(), 0);
+        assert_eq!(blocks.get(0), None);
+        assert_eq!(blocks.rank(10), 0);
+        assert!(!blocks.contains(0));
+        assert_eq!(blocks.iter().next(), None);
+        assert!(blocks.to_sequence().is_empty());
+    }
+}
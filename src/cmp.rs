@@ -0,0 +1,133 @@
+use crate::CompressedSequence;
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// Amount of values hashed at once
+const HASH_CHUNK: usize = 256;
+
+impl CompressedSequence {
+    /// Returns `true` if both sets have the exact same items
+    #[inline]
+    fn same_items(&self, other: &Self) -> bool {
+        self.step == other.step && self.seq == other.seq
+    }
+}
+
+impl PartialEq for CompressedSequence {
+    /// Two sets are equal if they contain the same values, regardless of their step size, item
+    /// layout or search indexes
+    fn eq(&self, other: &Self) -> bool {
+        if self.same_items(other) {
+            return true;
+        }
+
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for CompressedSequence {}
+
+impl Hash for CompressedSequence {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        // Chunks only depend on the values, so equal sets always produce the same writes
+        self.for_each_chunk(HASH_CHUNK, |chunk| u32::hash_slice(chunk, state));
+    }
+}
+
+impl PartialOrd for CompressedSequence {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompressedSequence {
+    /// Compares the values of both sets lexicographically
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.same_items(other) {
+            return Ordering::Equal;
+        }
+
+        self.iter().cmp(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{block::BlockSequence, index::IndexStrategy, test_utils::sample_values};
+    use std::collections::{hash_map::DefaultHasher, BTreeSet, HashSet};
+
+    fn hash(set: &CompressedSequence) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        set.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_eq_by_content() {
+        let values = sample_values();
+        let a = CompressedSequence::from_iterator(10, values.iter().copied());
+        let b = CompressedSequence::from_iterator(3, values.iter().copied());
+        let mut c = a.clone();
+        c.set_index_strategy(IndexStrategy::Both);
+        c.update_index(10.0);
+        // Splits sequences at block boundaries
+        let d = BlockSequence::from_sequence(&a).to_sequence();
+
+        for set in [&b, &c, &d] {
+            assert_eq!(&a, set);
+            assert_eq!(a.cmp(set), Ordering::Equal);
+            assert_eq!(hash(&a), hash(set));
+        }
+
+        let e = CompressedSequence::from_iterator(10, values[1..].iter().copied());
+        assert_ne!(a, e);
+        assert_ne!(a, CompressedSequence::new(10));
+        assert_eq!(CompressedSequence::new(1), CompressedSequence::new(7));
+    }
+
+    #[test]
+    fn test_hash_large_step() {
+        let a = CompressedSequence::from_iterator(1 << 31, [0, 1 << 31]);
+        let b = CompressedSequence::from_iterator(1, [0, 1 << 31]);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+
+        // Usable as keys of hashed collections
+        let set: HashSet<_> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_ord() {
+        let sets = [
+            vec![],
+            vec![0],
+            vec![0, 1, 2],
+            vec![0, 1, 2, 3],
+            vec![0, 2],
+            vec![1],
+            vec![1, 100, 200],
+        ];
+
+        let sets: Vec<CompressedSequence> = sets
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| CompressedSequence::from_iterator(i as u32 % 3 + 1, v))
+            .collect();
+
+        for (i, a) in sets.iter().enumerate() {
+            for (j, b) in sets.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j));
+            }
+        }
+
+        // Usable as keys of ordered collections
+        let tree: BTreeSet<_> = sets.iter().rev().cloned().collect();
+        assert!(tree.into_iter().eq(sets));
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{item::Item, CompressedSequence};
+use alloc::vec::Vec;
+use core::{fmt, num::NonZeroU16};
+
+/// Item tag of a single number
+const TAG_NUMBER: u64 = 0;
+/// Item tag of a number with a successor
+const TAG_NUMBERS: u64 = 1;
+/// Item tag of a sequence
+const TAG_SEQUENCE: u64 = 2;
+
+/// Error returned when decoding an invalid byte representation of a set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data ended unexpectedly
+    UnexpectedEnd,
+    /// An item had an unknown tag
+    InvalidTag(u8),
+    /// A value or length doesn't fit into its type
+    Overflow,
+    /// There are bytes left after the encoded set
+    TrailingBytes,
+    /// The data doesn't start with the magic bytes of its format
+    InvalidMagic,
+    /// The data was written by an unknown format version
+    UnsupportedVersion(u16),
+    /// The checksum of the payload doesn't match
+    InvalidChecksum,
+    /// The amount of decoded values doesn't match the header
+    LengthMismatch,
+    /// The step size is zero
+    ZeroStep,
+    /// An item doesn't start after the last number of the previous item
+    NotIncreasing,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of data"),
+            Self::InvalidTag(tag) => write!(f, "invalid item tag {tag}"),
+            Self::Overflow => write!(f, "value out of range"),
+            Self::TrailingBytes => write!(f, "trailing bytes after set"),
+            Self::InvalidMagic => write!(f, "missing magic bytes"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            Self::InvalidChecksum => write!(f, "checksum mismatch"),
+            Self::LengthMismatch => write!(f, "length doesn't match header"),
+            Self::ZeroStep => write!(f, "step size is zero"),
+            Self::NotIncreasing => write!(f, "items are not strictly increasing"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl CompressedSequence {
+    /// Encodes the set into a compact byte representation. Items are stored as variable
+    /// length integers relative to the previous item. Search indexes are not encoded
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.seq.len() * 3 + 10);
+        write_varint(&mut out, self.step as u64);
+        write_varint(&mut out, self.seq.len() as u64);
+        encode_items(&mut out, &self.seq, self.step);
+        out
+    }
+
+    /// Decodes a set from the byte representation created by [`to_bytes`]
+    ///
+    /// [`to_bytes`]: CompressedSequence::to_bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = bytes;
+        let step = read_step(&mut reader)?;
+        let count = read_varint(&mut reader)?;
+
+        let mut set = CompressedSequence::new(step);
+        set.seq = decode_items(&mut reader, count, step)?;
+
+        if !reader.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        Ok(set)
+    }
+}
+
+/// Appends all `items` to `out`, each relative to its predecessor
+pub(crate) fn encode_items(out: &mut Vec<u8>, items: &[Item], step: u32) {
+    let mut last = None;
+    for item in items {
+        encode_item(out, item, last);
+        last = Some(item.last_number(step));
+    }
+}
+
+/// Reads `count` items written by [`encode_items`] from the front of `reader`
+pub(crate) fn decode_items(
+    reader: &mut &[u8],
+    count: u64,
+    step: u32,
+) -> Result<Vec<Item>, DecodeError> {
+    // Don't trust the count for allocations, every item takes at least one byte
+    let mut items = Vec::with_capacity((count as usize).min(reader.len()));
+
+    let mut last = None;
+    for _ in 0..count {
+        let item = decode_item(reader, last, step)?;
+        last = Some(item.last_number(step));
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/// Appends `item` to `out`. The first number gets stored as distance to `last`, the last number
+/// of the previous item, or as it is if there is no previous item
+pub(crate) fn encode_item(out: &mut Vec<u8>, item: &Item, last: Option<u32>) {
+    let delta = (item.first_number() - last.unwrap_or(0)) as u64;
+    match item {
+        Item::Numbers(_, None) => write_varint(out, delta << 2 | TAG_NUMBER),
+        Item::Numbers(_, Some(next)) => {
+            write_varint(out, delta << 2 | TAG_NUMBERS);
+            write_varint(out, next.get() as u64);
+        }
+        Item::Sequence(_, cnt) => {
+            write_varint(out, delta << 2 | TAG_SEQUENCE);
+            write_varint(out, *cnt as u64);
+        }
+    }
+}
+
+/// Reads an item written by [`encode_item`] from the front of `reader`. Fails if the item
+/// doesn't start after `last`
+pub(crate) fn decode_item(
+    reader: &mut &[u8],
+    last: Option<u32>,
+    step: u32,
+) -> Result<Item, DecodeError> {
+    read_item(reader, step, |delta| match last {
+        Some(_) if delta == 0 => Err(DecodeError::NotIncreasing),
+        Some(last) => u32::try_from(last as u64 + delta).map_err(|_| DecodeError::Overflow),
+        None => u32::try_from(delta).map_err(|_| DecodeError::Overflow),
+    })
+}
+
+/// Reads an item of version 1 of the container format, which stores the first number as
+/// zigzag encoded difference to `last`. Fails if the item doesn't start after `last`
+pub(crate) fn decode_item_v1(
+    reader: &mut &[u8],
+    last: Option<u32>,
+    step: u32,
+) -> Result<Item, DecodeError> {
+    read_item(reader, step, |delta| {
+        let first = last.unwrap_or(0) as i64 + unzigzag(delta);
+        let first = u32::try_from(first).map_err(|_| DecodeError::Overflow)?;
+        match last {
+            Some(last) if first <= last => Err(DecodeError::NotIncreasing),
+            _ => Ok(first),
+        }
+    })
+}
+
+/// Reads a single item whose first number gets determined by `first` from the stored delta
+fn read_item<F>(reader: &mut &[u8], step: u32, first: F) -> Result<Item, DecodeError>
+where
+    F: FnOnce(u64) -> Result<u32, DecodeError>,
+{
+    let header = read_varint(reader)?;
+    let first = first(header >> 2)?;
+
+    let item = match header & 0b11 {
+        TAG_NUMBER => Item::Numbers(first, None),
+        TAG_NUMBERS => {
+            let next = u16::try_from(read_varint(reader)?)
+                .ok()
+                .and_then(NonZeroU16::new)
+                .ok_or(DecodeError::Overflow)?;
+            Item::Numbers(first, Some(next))
+        }
+        TAG_SEQUENCE => {
+            let cnt = u16::try_from(read_varint(reader)?).map_err(|_| DecodeError::Overflow)?;
+            Item::Sequence(first, cnt)
+        }
+        tag => return Err(DecodeError::InvalidTag(tag as u8)),
+    };
+
+    check_item(&item, step)?;
+    Ok(item)
+}
+
+/// Makes sure the last number of a decoded item can be computed without overflowing
+fn check_item(item: &Item, step: u32) -> Result<(), DecodeError> {
+    let last = match *item {
+        Item::Numbers(a, b) => a as u64 + b.map_or(0, |b| b.get() as u64),
+        Item::Sequence(start, cnt) => start as u64 + step as u64 * cnt as u64,
+    };
+    if last > u32::MAX as u64 {
+        return Err(DecodeError::Overflow);
+    }
+
+    Ok(())
+}
+
+/// Makes sure all `items` are valid and strictly increasing
+pub(crate) fn check_items(items: &[Item], step: u32) -> Result<(), DecodeError> {
+    check_step(step)?;
+
+    let mut last = None;
+    for item in items {
+        check_item(item, step)?;
+        if last.is_some_and(|last| item.first_number() <= last) {
+            return Err(DecodeError::NotIncreasing);
+        }
+        last = Some(item.last_number(step));
+    }
+
+    Ok(())
+}
+
+/// Appends `value` as LEB128 variable length integer
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a LEB128 variable length integer from the front of `reader`
+pub(crate) fn read_varint(reader: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = reader.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        *reader = rest;
+
+        value |= ((byte & 0x7f) as u64)
+            .checked_shl(shift)
+            .filter(|i| i >> shift == (byte & 0x7f) as u64)
+            .ok_or(DecodeError::Overflow)?;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(DecodeError::Overflow)
+}
+
+/// Reads a variable length integer that has to fit into an `u32`
+#[inline]
+pub(crate) fn read_u32(reader: &mut &[u8]) -> Result<u32, DecodeError> {
+    u32::try_from(read_varint(reader)?).map_err(|_| DecodeError::Overflow)
+}
+
+/// Reads a step size, which must not be zero
+#[inline]
+fn read_step(reader: &mut &[u8]) -> Result<u32, DecodeError> {
+    check_step(read_u32(reader)?)
+}
+
+/// Makes sure a decoded step size isn't zero
+#[inline]
+pub(crate) fn check_step(step: u32) -> Result<u32, DecodeError> {
+    if step == 0 {
+        return Err(DecodeError::ZeroStep);
+    }
+    Ok(step)
+}
+
+#[cfg(test)]
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[inline]
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sample_values;
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let set = CompressedSequence::from_iterator(3, sample_values());
+        for set in [set, CompressedSequence::new(7)] {
+            let bytes = set.to_bytes();
+            let decoded = CompressedSequence::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.step(), set.step());
+            assert_eq!(decoded.to_vec(), set.to_vec());
+            assert!(bytes.len() < set.seq.len() * 4 + 8);
+        }
+    }
+
+    #[test]
+    fn test_bytes_invalid() {
+        let bytes = CompressedSequence::from_iterator(3, sample_values()).to_bytes();
+
+        for len in 0..bytes.len() {
+            assert!(CompressedSequence::from_bytes(&bytes[..len]).is_err());
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            CompressedSequence::from_bytes(&trailing),
+            Err(DecodeError::TrailingBytes)
+        );
+
+        // step 1, one item with tag 3
+        assert_eq!(
+            CompressedSequence::from_bytes(&[1, 1, 0b011]),
+            Err(DecodeError::InvalidTag(3))
+        );
+
+        // step 1, one sequence starting at u32::MAX
+        let mut overflow = vec![1, 1];
+        write_varint(&mut overflow, (u32::MAX as u64) << 2 | TAG_SEQUENCE);
+        write_varint(&mut overflow, 1);
+        assert_eq!(
+            CompressedSequence::from_bytes(&overflow),
+            Err(DecodeError::Overflow)
+        );
+
+        // step 0, no items
+        assert_eq!(
+            CompressedSequence::from_bytes(&[0, 0]),
+            Err(DecodeError::ZeroStep)
+        );
+    }
+
+    #[test]
+    fn test_bytes_not_increasing() {
+        // step 1, sequence 10..=15 followed by the number 20
+        let mut bytes = vec![1, 2];
+        write_varint(&mut bytes, 10 << 2 | TAG_SEQUENCE);
+        write_varint(&mut bytes, 5);
+        write_varint(&mut bytes, 5 << 2 | TAG_NUMBER);
+        let set = CompressedSequence::from_bytes(&bytes).unwrap();
+        assert_eq!(set.to_vec(), [10, 11, 12, 13, 14, 15, 20]);
+
+        // The number 20 again
+        bytes[1] = 3;
+        write_varint(&mut bytes, TAG_NUMBER);
+        assert_eq!(
+            CompressedSequence::from_bytes(&bytes),
+            Err(DecodeError::NotIncreasing)
+        );
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut out = vec![];
+            write_varint(&mut out, value);
+            assert_eq!(read_varint(&mut out.as_slice()), Ok(value));
+        }
+
+        for value in [
+            0,
+            1,
+            -1,
+            i32::MIN as i64,
+            u32::MAX as i64,
+            -(u32::MAX as i64),
+        ] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+
+        assert_eq!(
+            read_varint(&mut [0xff; 11].as_slice()),
+            Err(DecodeError::Overflow)
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use crate::{
+    codec::{self, DecodeError},
+    get_cache::GetCache,
+    index::IndexStrategy,
+    item::Item,
+    CompressedSequence,
+};
+use alloc::vec::Vec;
+use core::num::NonZeroU16;
+
+/// Magic bytes every container starts with
+pub const MAGIC: [u8; 4] = *b"CSEQ";
+
+/// Format version written by [`CompressedSequence::to_versioned_bytes`]
+pub const VERSION: u16 = 2;
+
+/// Size of an encoded [`Header`] of the current version in bytes
+pub const HEADER_SIZE: usize = 35;
+
+/// Size of an encoded [`Header`] of version 1, which doesn't contain the index configuration
+const V1_HEADER_SIZE: usize = 30;
+
+/// Header of a versioned container, followed by the items in the format of
+/// [`CompressedSequence::to_bytes`]. All fields are stored in little endian after [`MAGIC`]
+/// in the order they are declared in.
+///
+/// Version 0 is the serde layout of a set written by bincode before the container format
+/// existed. It has no header and gets recognized by the missing magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// Format version
+    pub version: u16,
+    /// Step size of the set
+    pub step: u32,
+    /// Amount of encoded items
+    pub items: u64,
+    /// Amount of values in the set
+    pub len: u64,
+    /// CRC32 (IEEE) of all other header fields followed by the payload. Only covers the
+    /// payload in version 1
+    pub checksum: u32,
+    /// Strategy used to index the set. Since version 2
+    pub index_strategy: IndexStrategy,
+    /// Amount of items between two index entries or zero if the set isn't indexed. Since
+    /// version 2
+    pub index_stride: u32,
+}
+
+impl Header {
+    /// Reads the header from the front of `bytes`
+    pub fn read(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        let version = bytes.get(4..6).ok_or(DecodeError::UnexpectedEnd)?;
+        let version = u16::from_le_bytes(version.try_into().unwrap());
+        let size = match version {
+            1 => V1_HEADER_SIZE,
+            2 => HEADER_SIZE,
+            v => return Err(DecodeError::UnsupportedVersion(v)),
+        };
+
+        let header = bytes.get(..size).ok_or(DecodeError::UnexpectedEnd)?;
+        let mut out = Self {
+            version,
+            step: u32::from_le_bytes(header[6..10].try_into().unwrap()),
+            items: u64::from_le_bytes(header[10..18].try_into().unwrap()),
+            len: u64::from_le_bytes(header[18..26].try_into().unwrap()),
+            checksum: u32::from_le_bytes(header[26..30].try_into().unwrap()),
+            index_strategy: IndexStrategy::default(),
+            index_stride: 0,
+        };
+
+        if version >= 2 {
+            out.index_strategy = read_strategy(header[30] as u32)?;
+            out.index_stride = u32::from_le_bytes(header[31..35].try_into().unwrap());
+        }
+
+        Ok(out)
+    }
+
+    /// Appends the encoded header to `out`
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.step.to_le_bytes());
+        out.extend_from_slice(&self.items.to_le_bytes());
+        out.extend_from_slice(&self.len.to_le_bytes());
+        out.extend_from_slice(&self.checksum.to_le_bytes());
+
+        if self.version >= 2 {
+            out.push(match self.index_strategy {
+                IndexStrategy::None => 0,
+                IndexStrategy::Positional => 1,
+                IndexStrategy::Value => 2,
+                IndexStrategy::Both => 3,
+            });
+            out.extend_from_slice(&self.index_stride.to_le_bytes());
+        }
+    }
+
+    /// Calculates the checksum of the header fields and `payload`. The stored checksum itself
+    /// is left out
+    pub fn compute_checksum(&self, payload: &[u8]) -> u32 {
+        if self.version < 2 {
+            return crc32(payload);
+        }
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        Self {
+            checksum: 0,
+            ..*self
+        }
+        .write(&mut header);
+        crc32_parts(&[&header, payload])
+    }
+
+    /// Returns the size of the encoded header in bytes
+    #[inline]
+    pub fn size(&self) -> usize {
+        if self.version >= 2 {
+            HEADER_SIZE
+        } else {
+            V1_HEADER_SIZE
+        }
+    }
+}
+
+impl CompressedSequence {
+    /// Encodes the set into the current version of the container format. The search indexes
+    /// aren't encoded but get rebuilt with the same configuration when decoding
+    pub fn to_versioned_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.seq.len() * 3);
+        codec::encode_items(&mut payload, &self.seq, self.step);
+
+        let mut header = Header {
+            version: VERSION,
+            step: self.step,
+            items: self.seq.len() as u64,
+            len: self.len() as u64,
+            checksum: 0,
+            index_strategy: self.index_strategy,
+            index_stride: self.index_stride,
+        };
+        header.checksum = header.compute_checksum(&payload);
+
+        let mut out = Vec::with_capacity(HEADER_SIZE + payload.len());
+        header.write(&mut out);
+        out.extend_from_slice(&payload);
+        out
+    }
+
+    /// Decodes a set written by [`to_versioned_bytes`] of this or any older version. Data
+    /// without [`MAGIC`] gets migrated from version 0, the bincode serialized set
+    ///
+    /// [`to_versioned_bytes`]: CompressedSequence::to_versioned_bytes
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if !bytes.starts_with(&MAGIC) {
+            return read_legacy(bytes);
+        }
+
+        // Version 0 data of a set with the step size 0x5145_5343 starts with the magic bytes
+        // as well. It only passes all checks of a container if its checksum matches by chance
+        read_container(bytes).or_else(|err| read_legacy(bytes).map_err(|_| err))
+    }
+}
+
+/// Decodes a set stored in version 1 or later
+fn read_container(bytes: &[u8]) -> Result<CompressedSequence, DecodeError> {
+    let header = Header::read(bytes)?;
+    let payload = &bytes[header.size()..];
+    if header.compute_checksum(payload) != header.checksum {
+        return Err(DecodeError::InvalidChecksum);
+    }
+
+    let step = codec::check_step(header.step)?;
+    let mut reader = payload;
+    let mut set = CompressedSequence::new(step);
+    set.seq = match header.version {
+        1 => read_v1_items(&mut reader, header.items, step)?,
+        _ => codec::decode_items(&mut reader, header.items, step)?,
+    };
+
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    if set.len() as u64 != header.len {
+        return Err(DecodeError::LengthMismatch);
+    }
+
+    set.index_strategy = header.index_strategy;
+    set.index_stride = header.index_stride;
+    set.extend_index(0);
+    Ok(set)
+}
+
+/// Reads `count` items of a version 1 payload
+fn read_v1_items(reader: &mut &[u8], count: u64, step: u32) -> Result<Vec<Item>, DecodeError> {
+    // Don't trust the count for allocations, every item takes at least one byte
+    let mut items = Vec::with_capacity((count as usize).min(reader.len()));
+    let mut last = None;
+    for _ in 0..count {
+        let item = codec::decode_item_v1(reader, last, step)?;
+        last = Some(item.last_number(step));
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/// Decodes a set serialized by bincode in version 0. Bincode writes integers in little endian,
+/// lengths as `u64` and enum variants as `u32`. The stored positional index only determines
+/// the index stride while the entries get rebuilt from the decoded items. Sets serialized by
+/// later releases are followed by their index configuration
+fn read_legacy(bytes: &[u8]) -> Result<CompressedSequence, DecodeError> {
+    let mut reader = bytes;
+    let step = u32::from_le_bytes(read_array(&mut reader)?);
+
+    let count = u64::from_le_bytes(read_array(&mut reader)?);
+    // Don't trust the count for allocations, every item takes at least 9 bytes
+    let mut seq = Vec::with_capacity((count as usize).min(reader.len() / 9));
+    for _ in 0..count {
+        seq.push(read_legacy_item(&mut reader)?);
+    }
+    codec::check_items(&seq, step)?;
+
+    let entries = u64::from_le_bytes(read_array(&mut reader)?);
+    let mut index = GetCache::with_capacity((entries as usize).min(reader.len() / 12));
+    for _ in 0..entries {
+        let pos = u32::from_le_bytes(read_array(&mut reader)?);
+        let vec_pos = u32::from_le_bytes(read_array(&mut reader)?);
+        let len = u32::from_le_bytes(read_array(&mut reader)?);
+        index.insert(pos, vec_pos, len);
+    }
+
+    let mut set = CompressedSequence::from_parts(step, seq, index);
+    if !reader.is_empty() {
+        match read_array::<1>(&mut reader)?[0] {
+            0 => (),
+            1 => {
+                set.index_strategy = read_strategy(u32::from_le_bytes(read_array(&mut reader)?))?;
+                set.index_stride = u32::from_le_bytes(read_array(&mut reader)?);
+            }
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    set.clear_index();
+    set.extend_index(0);
+    Ok(set)
+}
+
+/// Maps the tag of an index strategy, which is its position in the declaration
+fn read_strategy(tag: u32) -> Result<IndexStrategy, DecodeError> {
+    let strategy = match tag {
+        0 => IndexStrategy::None,
+        1 => IndexStrategy::Positional,
+        2 => IndexStrategy::Value,
+        3 => IndexStrategy::Both,
+        tag => return Err(DecodeError::InvalidTag(tag.try_into().unwrap_or(u8::MAX))),
+    };
+
+    Ok(strategy)
+}
+
+/// Reads a single bincode encoded item
+fn read_legacy_item(reader: &mut &[u8]) -> Result<Item, DecodeError> {
+    let item = match u32::from_le_bytes(read_array(reader)?) {
+        0 => {
+            let value = u32::from_le_bytes(read_array(reader)?);
+            let next = match read_array::<1>(reader)?[0] {
+                0 => None,
+                1 => {
+                    let next = u16::from_le_bytes(read_array(reader)?);
+                    Some(NonZeroU16::new(next).ok_or(DecodeError::Overflow)?)
+                }
+                tag => return Err(DecodeError::InvalidTag(tag)),
+            };
+            Item::Numbers(value, next)
+        }
+        1 => {
+            let start = u32::from_le_bytes(read_array(reader)?);
+            Item::Sequence(start, u16::from_le_bytes(read_array(reader)?))
+        }
+        tag => return Err(DecodeError::InvalidTag(tag.try_into().unwrap_or(u8::MAX))),
+    };
+
+    Ok(item)
+}
+
+#[inline]
+fn read_array<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+    let (bytes, rest) = reader
+        .split_first_chunk::<N>()
+        .ok_or(DecodeError::UnexpectedEnd)?;
+    *reader = rest;
+    Ok(*bytes)
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Calculates the CRC32 (IEEE) checksum of `data`
+#[inline]
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_parts(&[data])
+}
+
+/// Calculates the CRC32 (IEEE) checksum of all `parts` concatenated
+fn crc32_parts(parts: &[&[u8]]) -> u32 {
+    !parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(!0, |crc, b| {
+            CRC_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_versioned_roundtrip() {
+        let mut values: Vec<u32> = (0..10_000).step_by(10).collect();
+        values.extend([10_001, 10_005, 80_000]);
+        values.extend((100_000..120_000).step_by(3));
+        let mut indexed = CompressedSequence::from_iterator(10, values.iter().copied());
+        indexed.set_index_strategy(IndexStrategy::Both);
+        indexed.update_index(10.0);
+
+        for set in [
+            CompressedSequence::from_iterator(10, values.iter().copied()),
+            indexed,
+            CompressedSequence::new(3),
+        ] {
+            let bytes = set.to_versioned_bytes();
+            assert!(bytes.starts_with(&MAGIC));
+
+            let header = Header::read(&bytes).unwrap();
+            assert_eq!(header.version, VERSION);
+            assert_eq!(header.step, set.step());
+            assert_eq!(header.items, set.seq().len() as u64);
+            assert_eq!(header.len, set.len() as u64);
+
+            let decoded = CompressedSequence::from_versioned_bytes(&bytes).unwrap();
+            assert_eq!(decoded.step(), set.step());
+            assert_eq!(decoded.to_vec(), set.to_vec());
+            assert_eq!(decoded.index_strategy(), set.index_strategy());
+            assert_eq!(decoded.index, set.index);
+            assert_eq!(decoded.value_index, set.value_index);
+        }
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        // Values [0, 3, 6, 9, 5000, 5002] with step 3. Version 1 stores the first number of
+        // an item as zigzag encoded difference to the previous item
+        let payload = [2, 3, 0xf9, 0xb7, 0x02, 2];
+        let header = Header {
+            version: 1,
+            step: 3,
+            items: 2,
+            len: 6,
+            checksum: crc32(&payload),
+            index_strategy: IndexStrategy::default(),
+            index_stride: 0,
+        };
+        let mut bytes = vec![];
+        header.write(&mut bytes);
+        bytes.extend_from_slice(&payload);
+        assert_eq!(Header::read(&bytes), Ok(header));
+        assert_eq!(header.size(), V1_HEADER_SIZE);
+
+        let decoded = CompressedSequence::from_versioned_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_vec(), [0, 3, 6, 9, 5000, 5002]);
+    }
+
+    #[test]
+    fn test_migrate_legacy() {
+        // Bincode output of the initial release: values [0, 10, 20, 30, 31, 50, 1000, 1001,
+        // 2000] with step 10 and an index entry for each item
+        let legacy: &[u8] = &[
+            10, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 31, 0,
+            0, 0, 1, 19, 0, 0, 0, 0, 0, 232, 3, 0, 0, 1, 1, 0, 0, 0, 0, 0, 208, 7, 0, 0, 0, 4, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 4, 0, 0,
+            0, 6, 0, 0, 0, 2, 0, 0, 0, 6, 0, 0, 0, 8, 0, 0, 0, 3, 0, 0, 0, 8, 0, 0, 0,
+        ];
+        let exp = [0, 10, 20, 30, 31, 50, 1000, 1001, 2000];
+
+        let decoded = CompressedSequence::from_versioned_bytes(legacy).unwrap();
+        assert_eq!(decoded.to_vec(), exp);
+        assert_eq!(decoded.index.len(), 4);
+        assert_eq!(decoded.get(6), Some(1000));
+
+        // Migrating is done by writing the set again
+        let bytes = decoded.to_versioned_bytes();
+        assert_eq!(Header::read(&bytes).unwrap().version, VERSION);
+        let decoded = CompressedSequence::from_versioned_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_vec(), exp);
+        assert_eq!(decoded.index.len(), 4);
+
+        for end in 0..legacy.len() {
+            assert!(CompressedSequence::from_versioned_bytes(&legacy[..end]).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_migrate_serde() {
+        let mut set = CompressedSequence::from_iterator(10, (0..10_000).step_by(7));
+        set.set_index_strategy(IndexStrategy::Value);
+        set.update_index(10.0);
+
+        let bytes = bincode::serialize(&set).unwrap();
+        let decoded = CompressedSequence::from_versioned_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_vec(), set.to_vec());
+        assert_eq!(decoded.index_strategy(), IndexStrategy::Value);
+        assert_eq!(decoded.value_index, set.value_index);
+        assert!(decoded.index.is_empty());
+
+        // Truncated index configuration. Without it the set is valid version 0 data
+        for end in bytes.len() - 8..bytes.len() {
+            assert!(CompressedSequence::from_versioned_bytes(&bytes[..end]).is_err());
+        }
+        let legacy = CompressedSequence::from_versioned_bytes(&bytes[..bytes.len() - 9]).unwrap();
+        assert_eq!(legacy.index_strategy(), IndexStrategy::Positional);
+    }
+
+    #[test]
+    fn test_migrate_legacy_magic() {
+        // Legacy set whose step size is encoded like the magic bytes
+        let step = u32::from_le_bytes(MAGIC);
+        let mut legacy = vec![];
+        legacy.extend_from_slice(&step.to_le_bytes());
+        legacy.extend_from_slice(&1u64.to_le_bytes());
+        legacy.extend_from_slice(&1u32.to_le_bytes());
+        legacy.extend_from_slice(&7u32.to_le_bytes());
+        legacy.extend_from_slice(&2u16.to_le_bytes());
+        legacy.extend_from_slice(&0u64.to_le_bytes());
+
+        let decoded = CompressedSequence::from_versioned_bytes(&legacy).unwrap();
+        assert_eq!(decoded.step(), step);
+        assert_eq!(decoded.to_vec(), [7, 7 + step, 7 + 2 * step]);
+    }
+
+    #[test]
+    fn test_versioned_corrupt() {
+        let values: Vec<u32> = (0..10_000).step_by(10).chain([10_001, 80_000]).collect();
+        let bytes = CompressedSequence::from_iterator(10, values).to_versioned_bytes();
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            CompressedSequence::from_versioned_bytes(&flipped),
+            Err(DecodeError::InvalidChecksum)
+        );
+
+        let mut version = bytes.clone();
+        version[4..6].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(
+            CompressedSequence::from_versioned_bytes(&version),
+            Err(DecodeError::UnsupportedVersion(3))
+        );
+
+        // The checksum covers the header fields as well
+        for pos in 6..HEADER_SIZE {
+            for bit in 0..8 {
+                let mut header = bytes.clone();
+                header[pos] ^= 1 << bit;
+                assert!(
+                    CompressedSequence::from_versioned_bytes(&header).is_err(),
+                    "flipped bit {bit} of byte {pos}"
+                );
+            }
+        }
+
+        let mut step = bytes.clone();
+        step[6] ^= 1;
+        assert_eq!(
+            CompressedSequence::from_versioned_bytes(&step),
+            Err(DecodeError::InvalidChecksum)
+        );
+
+        let mut strategy = bytes.clone();
+        strategy[30] = 4;
+        assert_eq!(
+            CompressedSequence::from_versioned_bytes(&strategy),
+            Err(DecodeError::InvalidTag(4))
+        );
+
+        assert_eq!(
+            CompressedSequence::from_versioned_bytes(&bytes[..HEADER_SIZE - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert!(CompressedSequence::from_versioned_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(Header::read(b"CSE"), Err(DecodeError::InvalidMagic));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::CompressedSequence;
+
+/// Positional cursor pointing to an item within a set. Passing the same cursor to
+/// subsequent [`CompressedSequence::get_cached`] calls makes lookups of nearby or
+/// increasing positions cheap since the item search continues where the last one stopped.
+///
+/// A cursor should only be used with a single set and has to be reset after modifying it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cursor {
+    /// Position of the current item
+    vec_pos: usize,
+    /// Amount of values stored in all items before the current item
+    i_len: usize,
+}
+
+impl Cursor {
+    /// Creates a new cursor pointing to the first item of a set
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new cursor pointing to the item at `vec_pos` with `i_len` values stored in
+    /// all items before it
+    #[inline]
+    pub(crate) fn at_item(vec_pos: usize, i_len: usize) -> Self {
+        Self { vec_pos, i_len }
+    }
+
+    /// Moves the cursor back to the first item
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Moves the cursor to the item holding the value at `pos`. Returns the position of the
+    /// item and the amount of values stored in all items before it or `None` if `pos` is out
+    /// of range. The cursor stays at the last item in this case
+    pub(crate) fn seek(&mut self, set: &CompressedSequence, pos: usize) -> Option<(usize, usize)> {
+        let valid = self.vec_pos < set.seq.len() && pos >= self.i_len;
+        if valid && pos < self.i_len + set.seq[self.vec_pos].len() {
+            return Some((self.vec_pos, self.i_len));
+        }
+
+        // Continue from the closest indexed item if it's ahead of the cursor. Seeking backwards
+        // has to start from there or the first item anyways
+        let key = u32::try_from(pos).unwrap_or(u32::MAX);
+        match set.index.get(key) {
+            Some((_, vec_pos, len)) if !valid || len as usize > self.i_len => {
+                *self = Cursor {
+                    vec_pos: vec_pos as usize,
+                    i_len: len as usize,
+                };
+            }
+            None if !valid => self.reset(),
+            _ => (),
+        }
+
+        loop {
+            let next_len = self.i_len + set.seq.get(self.vec_pos)?.len();
+            if pos < next_len {
+                return Some((self.vec_pos, self.i_len));
+            }
+
+            if self.vec_pos + 1 == set.seq.len() {
+                return None;
+            }
+
+            self.i_len = next_len;
+            self.vec_pos += 1;
+        }
+    }
+
+    /// Moves the cursor to the last item and returns the amount of values in the set
+    pub(crate) fn seek_end(&mut self, set: &CompressedSequence) -> usize {
+        let Some(last) = set.seq.last() else {
+            return 0;
+        };
+
+        // Always fails at the last item
+        let _ = self.seek(set, usize::MAX);
+        self.i_len + last.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cursor;
+    use crate::{item::Item, test_utils::sample_set, CompressedSequence};
+
+    #[test]
+    fn test_cursor_forward() {
+        let (comp_seq, exp) = sample_set(10);
+        let mut cursor = Cursor::new();
+
+        for (pos, i) in exp.iter().enumerate() {
+            assert_eq!(comp_seq.get_cached(pos, &mut cursor), Some(*i));
+        }
+    }
+
+    #[test]
+    fn test_cursor_backward() {
+        let (mut comp_seq, exp) = sample_set(10);
+
+        for indexed in [false, true] {
+            if indexed {
+                comp_seq.update_index(10.0);
+            }
+
+            let mut cursor = Cursor::new();
+            for (pos, i) in exp.iter().enumerate().rev() {
+                assert_eq!(comp_seq.get_cached(pos, &mut cursor), Some(*i));
+            }
+
+            for pos in (0..exp.len())
+                .step_by(7)
+                .chain((0..exp.len()).step_by(5).rev())
+            {
+                assert_eq!(comp_seq.get_cached(pos, &mut cursor), Some(exp[pos]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cursor_out_of_range() {
+        let (mut comp_seq, exp) = sample_set(10);
+        let len = exp.len();
+
+        for indexed in [false, true] {
+            if indexed {
+                comp_seq.update_index(10.0);
+            }
+
+            for start in (0..len).step_by(37) {
+                for pos in [len, len + 1, len + 1000, u32::MAX as usize, usize::MAX] {
+                    let mut cursor = Cursor::new();
+                    assert_eq!(comp_seq.get_cached(start, &mut cursor), Some(exp[start]));
+                    assert_eq!(comp_seq.get_cached(pos, &mut cursor), None);
+                    assert_eq!(comp_seq.get(pos), None);
+
+                    // The cursor must still be usable after a miss
+                    assert_eq!(comp_seq.get_cached(start, &mut cursor), Some(exp[start]));
+                    assert_eq!(
+                        comp_seq.get_cached(len - 1, &mut cursor),
+                        exp.last().copied()
+                    );
+                }
+            }
+        }
+
+        let empty = CompressedSequence::new(10);
+        let mut cursor = Cursor::new();
+        for pos in 0..10 {
+            assert_eq!(empty.get_cached(pos, &mut cursor), None);
+        }
+    }
+
+    #[test]
+    fn test_cursor_uses_index() {
+        let (mut comp_seq, exp) = sample_set(10);
+        comp_seq.update_index(100.0);
+
+        // Grow the second item without updating the index. Lookups starting from the index
+        // still find the old values while scanning all items would be off by 1000 values
+        let skip = comp_seq.seq[0].len() + comp_seq.seq[1].len();
+        comp_seq.seq_mut()[1] = Item::Sequence(10, 1000);
+
+        for (pos, i) in exp.iter().enumerate().skip(skip) {
+            assert_eq!(comp_seq.get(pos), Some(*i));
+            assert_eq!(comp_seq.get_cached(pos, &mut Cursor::new()), Some(*i));
+        }
+
+        // Forward seeks skip ahead as well
+        let mut cursor = Cursor::new();
+        assert_eq!(comp_seq.get_cached(skip, &mut cursor), Some(exp[skip]));
+        assert_eq!(
+            comp_seq.get_cached(exp.len() - 1, &mut cursor),
+            exp.last().copied()
+        );
+    }
+
+    #[test]
+    fn test_cursor_other_set() {
+        let (comp_seq, exp) = sample_set(10);
+        let small = CompressedSequence::from_iterator(10, [1, 2, 3]);
+
+        let mut cursor = Cursor::new();
+        assert_eq!(
+            comp_seq.get_cached(exp.len() - 1, &mut cursor),
+            exp.last().copied()
+        );
+        assert_eq!(small.get_cached(1, &mut cursor), Some(2));
+        assert_eq!(small.get_cached(3, &mut cursor), None);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{block::BlockSequence, item::Item, CompressedSequence};
+use alloc::vec::Vec;
+
+impl CompressedSequence {
+    /// Appends all values of the set to `out`. Sequence items are expanded in bulk which is
+    /// considerably faster than collecting [`iter`](CompressedSequence::iter)
+    pub fn decode_into(&self, out: &mut Vec<u32>) {
+        out.reserve(self.len());
+        for item in self.seq.iter() {
+            extend_item(out, *item, self.step);
+        }
+    }
+
+    /// Decodes the set in chunks of `chunk_size` values and calls `f` for each of them. Only the
+    /// last chunk can be smaller. This avoids decoding the whole set into memory at once
+    ///
+    /// # Panics
+    /// panics if `chunk_size` is zero
+    pub fn for_each_chunk<F>(&self, chunk_size: usize, mut f: F)
+    where
+        F: FnMut(&[u32]),
+    {
+        assert!(chunk_size > 0, "Chunk size must not be zero");
+
+        let mut buf = Vec::with_capacity(chunk_size.min(self.len()));
+        for item in self.seq.iter() {
+            let (mut start, mut len) = match *item {
+                Item::Numbers(..) => {
+                    for value in item.iter(self.step) {
+                        buf.push(value);
+                        if buf.len() == chunk_size {
+                            f(&buf);
+                            buf.clear();
+                        }
+                    }
+                    continue;
+                }
+                Item::Sequence(start, cnt) => (start, cnt as usize + 1),
+            };
+
+            while len > 0 {
+                let take = len.min(chunk_size - buf.len());
+                extend_run(&mut buf, start, self.step, take);
+                len -= take;
+                if len > 0 {
+                    // Only advanced within the run since its end might be close to u32::MAX
+                    start += take as u32 * self.step;
+                }
+
+                if buf.len() == chunk_size {
+                    f(&buf);
+                    buf.clear();
+                }
+            }
+        }
+
+        if !buf.is_empty() {
+            f(&buf);
+        }
+    }
+}
+
+impl BlockSequence {
+    /// Appends all values of the set to `out`
+    pub fn decode_into(&self, out: &mut Vec<u32>) {
+        out.reserve(self.len());
+        for block in 0..self.block_count() {
+            for item in self.items(block) {
+                extend_item(out, item, self.step());
+            }
+        }
+    }
+}
+
+/// Appends the values of `item` to `out`
+#[inline]
+fn extend_item(out: &mut Vec<u32>, item: Item, step: u32) {
+    match item {
+        Item::Numbers(a, None) => out.push(a),
+        Item::Numbers(a, Some(b)) => out.extend([a, a + b.get() as u32]),
+        Item::Sequence(start, cnt) => extend_run(out, start, step, cnt as usize + 1),
+    }
+}
+
+/// Appends `len` values starting at `start` with a distance of `step` to `out`. Simple enough
+/// to be auto vectorized by the compiler
+#[inline]
+fn extend_run(out: &mut Vec<u32>, start: u32, step: u32, len: usize) {
+    out.extend((0..len as u32).map(|i| start + i * step));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sample_values;
+
+    #[test]
+    fn test_extend_run() {
+        for len in 0..20 {
+            for (start, step) in [(0, 1), (7, 3), (u32::MAX - 3 * len as u32, 3)] {
+                let mut out = vec![42];
+                extend_run(&mut out, start, step, len);
+                let exp: Vec<u32> = (0..len as u32).map(|i| start + i * step).collect();
+                assert_eq!(out[1..], exp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_into() {
+        for step in [1, 3, 5, 10] {
+            let set = CompressedSequence::from_iterator(step, sample_values());
+            let exp: Vec<u32> = set.iter().collect();
+
+            let mut out = vec![42];
+            set.decode_into(&mut out);
+            assert_eq!(out[0], 42);
+            assert_eq!(out[1..], exp);
+            assert_eq!(set.to_vec(), exp);
+
+            let mut out = vec![];
+            BlockSequence::from_sequence(&set).decode_into(&mut out);
+            assert_eq!(out, exp);
+        }
+
+        assert!(CompressedSequence::new(1).to_vec().is_empty());
+    }
+
+    #[test]
+    fn test_for_each_chunk() {
+        // The last run ends at u32::MAX
+        let set = CompressedSequence::from_iterator(5, sample_values());
+        let exp = set.to_vec();
+
+        for chunk_size in [1, 3, 4, 128, 1000, exp.len(), exp.len() + 1] {
+            let mut out = vec![];
+            let mut chunks = 0;
+            set.for_each_chunk(chunk_size, |chunk| {
+                assert!(!chunk.is_empty() && chunk.len() <= chunk_size);
+                // Only the last chunk can be smaller
+                assert_eq!(out.len(), chunks * chunk_size);
+                out.extend_from_slice(chunk);
+                chunks += 1;
+            });
+            assert_eq!(out, exp);
+            assert_eq!(chunks, exp.len().div_ceil(chunk_size));
+        }
+
+        CompressedSequence::new(1).for_each_chunk(10, |_| panic!("Empty set has no chunks"));
+    }
+
+    #[test]
+    fn test_for_each_chunk_large_step() {
+        for step in [1 << 31, u32::MAX / 3] {
+            let set =
+                CompressedSequence::from_iterator(step, (0..=u32::MAX).step_by(step as usize));
+            let exp = set.to_vec();
+            assert!(exp.len() > 1);
+
+            for chunk_size in [1, 2, exp.len()] {
+                let mut out = vec![];
+                set.for_each_chunk(chunk_size, |chunk| out.extend_from_slice(chunk));
+                assert_eq!(out, exp);
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{
+    codec::{self, DecodeError},
+    item::Item,
+    stream::{self, IndexEntry, SequenceReader, Trailer},
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Amount of items per block if the file has no index
+const SCAN_STRIDE: u64 = 64;
+
+/// Default amount of blocks kept in memory
+const DEFAULT_CACHE_BLOCKS: usize = 64;
+
+/// Read only set stored in a file written by [`SequenceWriter`]. Only the sparse index is kept
+/// in memory. Items get read in blocks ranging from one index entry to the next one and the
+/// most recently used blocks are cached. Files without an index get scanned once on opening.
+///
+/// [`SequenceWriter`]: crate::stream::SequenceWriter
+pub struct FileSequence {
+    file: Mutex<File>,
+    step: u32,
+    len: u64,
+    /// Byte offset of the end marker
+    items_end: u64,
+    index: Vec<IndexEntry>,
+    cache: Mutex<BlockCache>,
+}
+
+/// Least recently used blocks of a file
+struct BlockCache {
+    /// Block => (items, last usage)
+    blocks: HashMap<usize, (Arc<Vec<Item>>, u64)>,
+    capacity: usize,
+    tick: u64,
+}
+
+impl FileSequence {
+    /// Opens the set stored at `path`
+    #[inline]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::with_cache(path, DEFAULT_CACHE_BLOCKS)
+    }
+
+    /// Opens the set stored at `path` caching up to `blocks` blocks
+    pub fn with_cache<P: AsRef<Path>>(path: P, blocks: usize) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let step = stream::read_header(&mut file)?;
+
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let trailer_offset = file
+            .seek(SeekFrom::End(-8))
+            .and_then(|_| stream::read_u64(&mut file))?;
+        if trailer_offset <= stream::HEADER_SIZE || trailer_offset > file_len - 8 {
+            return Err(stream::invalid_data(DecodeError::UnexpectedEnd));
+        }
+
+        file.seek(SeekFrom::Start(trailer_offset))?;
+        let trailer = Trailer::read(&mut (&mut file).take(file_len - 8 - trailer_offset), true)?;
+        let items_end = trailer_offset - 1;
+
+        let index = if trailer.index.is_empty() && trailer.items > 0 {
+            file.seek(SeekFrom::Start(0))?;
+            scan_index(&file)?
+        } else {
+            trailer.index
+        };
+        check_index(&index, trailer.len, items_end)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            step,
+            len: trailer.len,
+            items_end,
+            index,
+            cache: Mutex::new(BlockCache {
+                blocks: HashMap::new(),
+                capacity: blocks.max(1),
+                tick: 0,
+            }),
+        })
+    }
+
+    /// Returns the value at position `pos`
+    pub fn get(&self, pos: usize) -> io::Result<Option<u32>> {
+        if pos as u64 >= self.len {
+            return Ok(None);
+        }
+
+        let block = self.index.partition_point(|i| i.position <= pos as u64) - 1;
+        let mut pos = pos - self.index[block].position as usize;
+        for item in self.block(block)?.iter() {
+            if pos < item.len() {
+                return Ok(item.at(pos, self.step));
+            }
+            pos -= item.len();
+        }
+
+        Ok(None)
+    }
+
+    /// Returns `true` if the set contains `value`
+    pub fn contains(&self, value: u32) -> io::Result<bool> {
+        let block = self.index.partition_point(|i| i.value <= value);
+        if block == 0 {
+            return Ok(false);
+        }
+
+        let items = self.block(block - 1)?;
+        let pos = items.partition_point(|i| i.last_number(self.step) < value);
+        Ok(items.get(pos).is_some_and(|i| i.contains(value, self.step)))
+    }
+
+    /// Returns an iterator over all values. Blocks are read without using the block cache
+    #[inline]
+    pub fn iter(&self) -> FileIter<'_> {
+        FileIter {
+            set: self,
+            block: 0,
+            items: Arc::default(),
+            pos: 0,
+            ipos: 0,
+        }
+    }
+
+    /// Returns the amount of values in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the set is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the step size of the set
+    #[inline]
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    /// Returns the items of a block, reading it if it isn't cached
+    fn block(&self, block: usize) -> io::Result<Arc<Vec<Item>>> {
+        let mut cache = self.cache();
+        cache.tick += 1;
+        let tick = cache.tick;
+        if let Some((items, last_used)) = cache.blocks.get_mut(&block) {
+            *last_used = tick;
+            return Ok(items.clone());
+        }
+        drop(cache);
+
+        let items = Arc::new(self.read_block(block)?);
+
+        let mut cache = self.cache();
+        if cache.blocks.len() >= cache.capacity {
+            let lru = cache
+                .blocks
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(block, _)| *block);
+            if let Some(lru) = lru {
+                cache.blocks.remove(&lru);
+            }
+        }
+        cache.blocks.insert(block, (items.clone(), tick));
+
+        Ok(items)
+    }
+
+    /// Reads and decodes all items of a block
+    fn read_block(&self, block: usize) -> io::Result<Vec<Item>> {
+        let entry = self.index[block];
+        let end = self
+            .index
+            .get(block + 1)
+            .map_or(self.items_end, |i| i.offset);
+
+        let mut buf = vec![0; (end - entry.offset) as usize];
+        {
+            let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut buf)?;
+        }
+
+        let mut reader = buf.as_slice();
+        let mut items = vec![];
+        let mut last = entry.last();
+        while !reader.is_empty() {
+            let item =
+                codec::decode_item(&mut reader, last, self.step).map_err(stream::invalid_data)?;
+            last = Some(item.last_number(self.step));
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+
+    #[inline]
+    fn cache(&self) -> MutexGuard<'_, BlockCache> {
+        // Cached blocks are always valid so there is nothing to recover from a poisoned lock
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Builds an index by reading all items of the file
+fn scan_index(file: &File) -> io::Result<Vec<IndexEntry>> {
+    let mut reader = SequenceReader::new(file)?;
+    let mut index = vec![];
+    let mut items = 0u64;
+    while let Some((_, entry)) = reader.next_item()? {
+        if items.is_multiple_of(SCAN_STRIDE) {
+            index.push(entry);
+        }
+        items += 1;
+    }
+    Ok(index)
+}
+
+/// Makes sure the index can't point outside of the items
+fn check_index(index: &[IndexEntry], len: u64, items_end: u64) -> io::Result<()> {
+    let first_valid = index.first().map_or(len == 0, |i| {
+        i.position == 0 && i.offset == stream::HEADER_SIZE
+    });
+    let sorted = index.windows(2).all(|w| {
+        w[0].offset < w[1].offset && w[0].position < w[1].position && w[0].value < w[1].value
+    });
+    let in_bounds = index
+        .last()
+        .is_none_or(|i| i.offset < items_end && i.position < len);
+
+    if first_valid && sorted && in_bounds {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "invalid index"))
+    }
+}
+
+/// Iterator over all values of a [`FileSequence`]
+pub struct FileIter<'a> {
+    set: &'a FileSequence,
+    /// Next block to read
+    block: usize,
+    items: Arc<Vec<Item>>,
+    pos: usize,
+    ipos: usize,
+}
+
+impl<'a> Iterator for FileIter<'a> {
+    type Item = io::Result<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.get(self.pos) {
+                if let Some(val) = item.at(self.ipos, self.set.step) {
+                    self.ipos += 1;
+                    return Some(Ok(val));
+                }
+
+                self.ipos = 0;
+                self.pos += 1;
+                continue;
+            }
+
+            if self.block >= self.set.index.len() {
+                return None;
+            }
+
+            let block = self.block;
+            self.block += 1;
+            self.pos = 0;
+            match self.set.read_block(block) {
+                Ok(items) => self.items = Arc::new(items),
+                Err(err) => {
+                    // Stop after the first error
+                    self.block = self.set.index.len();
+                    self.items = Arc::default();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{stream::SequenceWriter, test_utils::sample_values};
+    use std::{fs, io::BufWriter, path::PathBuf};
+
+    /// Temporary file which gets removed on drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("compressed_set_{}_{name}", std::process::id());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn write_file(name: &str, values: &[u32], rate: f64) -> TempFile {
+        let file = TempFile::new(name);
+        let out = BufWriter::new(File::create(&file.0).unwrap());
+        let mut writer = SequenceWriter::with_index(out, 10, rate).unwrap();
+        for v in values {
+            writer.push(*v).unwrap();
+        }
+        writer.finish().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_file_sequence() {
+        let values = sample_values();
+        for (name, rate) in [("indexed", 5.0), ("scanned", 0.0)] {
+            let file = write_file(name, &values, rate);
+            let set = FileSequence::with_cache(&file.0, 4).unwrap();
+            assert_eq!(set.len(), values.len());
+            assert_eq!(set.step(), 10);
+
+            for (pos, value) in values.iter().enumerate() {
+                assert_eq!(set.get(pos).unwrap(), Some(*value));
+                assert!(set.contains(*value).unwrap());
+            }
+            assert_eq!(set.get(values.len()).unwrap(), None);
+            assert!(set.cache().blocks.len() <= 4);
+
+            for value in [1, 9_999, 10_002, 300_001, 400_001, u32::MAX - 1] {
+                assert!(!set.contains(value).unwrap());
+            }
+
+            let iterated: Vec<u32> = set.iter().map(|i| i.unwrap()).collect();
+            assert_eq!(iterated, values);
+        }
+    }
+
+    #[test]
+    fn test_file_sequence_empty() {
+        let file = write_file("empty", &[], 10.0);
+        let set = FileSequence::open(&file.0).unwrap();
+        assert!(set.is_empty());
+        assert_eq!(set.get(0).unwrap(), None);
+        assert!(!set.contains(0).unwrap());
+        assert!(set.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_file_sequence_invalid() {
+        let file = write_file("invalid", &sample_values(), 5.0);
+        let bytes = fs::read(&file.0).unwrap();
+
+        // Truncated files
+        for len in [0, 8, bytes.len() / 2, bytes.len() - 1] {
+            fs::write(&file.0, &bytes[..len]).unwrap();
+            assert!(FileSequence::open(&file.0).is_err());
+        }
+
+        // Corrupt item within the second block
+        let set = {
+            fs::write(&file.0, &bytes).unwrap();
+            FileSequence::open(&file.0).unwrap()
+        };
+        let mut corrupt = bytes.clone();
+        let offset = set.index[1].offset as usize;
+        corrupt[offset] = 0b11;
+        fs::write(&file.0, &corrupt).unwrap();
+
+        let set = FileSequence::open(&file.0).unwrap();
+        assert!(set.get(0).is_ok());
+        assert!(set.get(set.index[1].position as usize).is_err());
+        assert!(set.iter().any(|i| i.is_err()));
+    }
+}
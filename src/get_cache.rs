@@ -1,6 +1,12 @@
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// Cache entry in format (pos, vec_pos, len)
+type Entry = (u32, u32, u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GetCache {
     // (pos, vec_pos, len)
     cache: Vec<(u32, u32, u32)>,
@@ -21,11 +27,8 @@ impl GetCache {
 
     #[inline]
     pub fn insert(&mut self, pos: u32, vec_pos: u32, len: u32) {
-        match self.cache.binary_search_by(|i| i.0.cmp(&pos)) {
-            Ok(_) => return,
-            Err(new_pos) => {
-                self.cache.insert(new_pos, (pos, vec_pos, len));
-            }
+        if let Err(new_pos) = self.cache.binary_search_by(|i| i.0.cmp(&pos)) {
+            self.cache.insert(new_pos, (pos, vec_pos, len));
         }
     }
 
@@ -36,10 +39,58 @@ impl GetCache {
         }
     }
 
+    /// Returns the entry with the greatest key not greater than `pos` and the entry following it
+    pub(crate) fn get_bounds(&self, pos: u32) -> (Option<Entry>, Option<Entry>) {
+        let next = match self.cache.binary_search_by(|i| i.0.cmp(&pos)) {
+            Ok(cpos) => cpos + 1,
+            Err(tpos) => tpos,
+        };
+        let prev = next.checked_sub(1).map(|i| self.cache[i]);
+        (prev, self.cache.get(next).copied())
+    }
+
+    /// Returns the entry with the greatest key
+    #[inline]
+    pub(crate) fn last(&self) -> Option<Entry> {
+        self.cache.last().copied()
+    }
+
+    /// Returns the distance between the items of the first two entries
+    #[inline]
+    pub(crate) fn item_stride(&self) -> Option<u32> {
+        match self.cache[..] {
+            [(_, first, _), (_, second, _), ..] => Some(second - first),
+            _ => None,
+        }
+    }
+
+    /// Removes all entries pointing to an item at or after `vec_pos`
+    #[inline]
+    pub(crate) fn truncate_items(&mut self, vec_pos: usize) {
+        let keep = self.cache.partition_point(|i| (i.1 as usize) < vec_pos);
+        self.cache.truncate(keep);
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.cache.len()
     }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Returns the amount of bytes allocated for entries, including unused capacity
+    #[inline]
+    pub fn size_of(&self) -> usize {
+        self.cache.capacity() * core::mem::size_of::<Entry>()
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.cache.shrink_to_fit();
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,31 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Strategy used to build the search indexes of a set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IndexStrategy {
+    /// Don't build any index
+    None,
+    /// Index items by their position within the set
+    #[default]
+    Positional,
+    /// Index items by their first value
+    Value,
+    /// Index items by their position and by their first value
+    Both,
+}
+
+impl IndexStrategy {
+    /// Returns `true` if items should be indexed by position
+    #[inline]
+    pub fn by_position(&self) -> bool {
+        matches!(self, Self::Positional | Self::Both)
+    }
+
+    /// Returns `true` if items should be indexed by value
+    #[inline]
+    pub fn by_value(&self) -> bool {
+        matches!(self, Self::Value | Self::Both)
+    }
+}
@@ -1,8 +1,10 @@
+use core::num::NonZeroU16;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::num::NonZeroU16;
 
 /// A number item within a set
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) enum Item {
     /// Number and successor
     Numbers(u32, Option<NonZeroU16>),
@@ -48,11 +50,26 @@ impl Item {
                 _ => None,
             },
             Item::Sequence(start, cnt) => {
-                ((pos as u16) <= *cnt).then(|| start + (pos as u32 * step_size))
+                (pos <= *cnt as usize).then(|| start + (pos as u32 * step_size))
             }
         }
     }
 
+    /// Returns an iterator over all numbers of the item
+    #[inline]
+    pub fn iter(self, step_size: u32) -> impl Iterator<Item = u32> {
+        (0..self.len()).filter_map(move |pos| self.at(pos, step_size))
+    }
+
+    /// Returns the first number of an item
+    #[inline]
+    pub fn first_number(&self) -> u32 {
+        match self {
+            Item::Numbers(a, _) => *a,
+            Item::Sequence(start, _) => *start,
+        }
+    }
+
     /// Returns the last number of an item
     #[inline]
     pub fn last_number(&self, step_size: u32) -> u32 {
@@ -84,6 +101,94 @@ impl Item {
         }
     }
 
+    /// Returns the position of the first number within the item that is not smaller
+    /// than `value`. Returns the items length if all numbers are smaller
+    pub fn lower_bound(&self, value: u32, step_size: u32) -> usize {
+        match self {
+            Item::Numbers(a, b) => {
+                if value <= *a {
+                    0
+                } else if b.is_some_and(|b| value <= a + b.get() as u32) {
+                    1
+                } else {
+                    self.len()
+                }
+            }
+            Item::Sequence(start, cnt) => {
+                if value <= *start {
+                    return 0;
+                }
+                let step_size = step_size.max(1) as u64;
+                let pos = (value - start) as u64 + step_size - 1;
+                ((pos / step_size) as usize).min(*cnt as usize + 1)
+            }
+        }
+    }
+
+    /// Returns `true` if the item contains `value`
+    #[inline]
+    pub fn contains(&self, value: u32, step_size: u32) -> bool {
+        self.at(self.lower_bound(value, step_size), step_size) == Some(value)
+    }
+
+    /// Splits the item into two items where the second one starts with the number
+    /// at `pos`
+    ///
+    /// # Panics
+    /// panics if `pos` is zero or not smaller than the items length
+    pub fn split(self, pos: usize, step_size: u32) -> (Self, Self) {
+        assert!(pos > 0 && pos < self.len(), "Split position out of bounds");
+
+        match self {
+            Item::Numbers(a, b) => (Self::new(a), Self::new(a + b.unwrap().get() as u32)),
+            Item::Sequence(start, cnt) => {
+                let right_start = start + pos as u32 * step_size;
+                let right_cnt = cnt - pos as u16;
+                (
+                    Self::run(start, pos as u16 - 1),
+                    Self::run(right_start, right_cnt),
+                )
+            }
+        }
+    }
+
+    /// Creates a sequence item with `cnt` steps or a single number if there are no steps
+    #[inline]
+    fn run(start: u32, cnt: u16) -> Self {
+        if cnt == 0 {
+            Self::new(start)
+        } else {
+            Self::Sequence(start, cnt)
+        }
+    }
+
+    /// Tries to add `value` to the end of the item. Returns `false` if the value doesn't
+    /// fit into the item and a new item is required
+    pub(crate) fn try_add(&mut self, value: u32, step_size: u32) -> bool {
+        if !self.can_add() {
+            return false;
+        }
+
+        if self.last_number(step_size).checked_add(step_size) == Some(value) {
+            *self = self.to_sequence();
+            self.seq_add();
+            return true;
+        }
+
+        if let Item::Numbers(nr, next) = self {
+            if value == *nr {
+                panic!("Can't push the same value twice");
+            }
+
+            if value > *nr && value - *nr <= u16::MAX as u32 {
+                *next = NonZeroU16::new((value - *nr) as u16);
+                return true;
+            }
+        }
+
+        false
+    }
+
     /// Returns `true` if there can be a value added
     #[inline]
     pub fn can_add(&self) -> bool {
@@ -2,7 +2,8 @@ use crate::{item::Item, CompressedSequence};
 
 /// Iterator over a CompressedSequence
 pub struct CompSeqIterRef<'a> {
-    set: &'a CompressedSequence,
+    items: &'a [Item],
+    step: u32,
     pos: usize,
     ipos: usize,
     item: Option<&'a Item>,
@@ -11,16 +12,58 @@ pub struct CompSeqIterRef<'a> {
 impl<'a> CompSeqIterRef<'a> {
     #[inline]
     pub fn new(set: &'a CompressedSequence) -> Self {
-        let item = set.seq().get(0);
+        Self::from_items(set.seq(), set.step)
+    }
+
+    /// Creates a new iterator over the values of `items`
+    #[inline]
+    pub(crate) fn from_items(items: &'a [Item], step: u32) -> Self {
+        Self::from_position(items, step, 0, 0)
+    }
+
+    /// Creates a new iterator over the values of `items` starting at the value `ipos` of the
+    /// item at `pos`
+    #[inline]
+    pub(crate) fn from_position(items: &'a [Item], step: u32, pos: usize, ipos: usize) -> Self {
         Self {
-            set,
-            pos: 0,
-            ipos: 0,
-            item,
+            items,
+            step,
+            pos,
+            ipos,
+            item: items.get(pos),
         }
     }
 }
 
+/// Iterator over all values of a CompressedSequence within a range
+pub struct RangeIter<'a> {
+    iter: CompSeqIterRef<'a>,
+    /// Exclusive upper bound of the range
+    end: u64,
+}
+
+impl<'a> RangeIter<'a> {
+    #[inline]
+    pub(crate) fn new(iter: CompSeqIterRef<'a>, end: u64) -> Self {
+        Self { iter, end }
+    }
+}
+
+impl<'a> Iterator for RangeIter<'a> {
+    type Item = u32;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.iter.next()?;
+        if value as u64 >= self.end {
+            // Make sure the iterator stays exhausted
+            self.end = 0;
+            return None;
+        }
+        Some(value)
+    }
+}
+
 impl<'a> Iterator for CompSeqIterRef<'a> {
     type Item = u32;
 
@@ -28,14 +71,14 @@ impl<'a> Iterator for CompSeqIterRef<'a> {
         loop {
             let curr_item = self.item?;
 
-            if let Some(val) = curr_item.at(self.ipos, self.set.step) {
+            if let Some(val) = curr_item.at(self.ipos, self.step) {
                 self.ipos += 1;
                 return Some(val);
             }
 
             self.ipos = 0;
             self.pos += 1;
-            self.item = self.set.seq().get(self.pos);
+            self.item = self.items.get(self.pos);
         }
     }
 }
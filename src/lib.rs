@@ -1,54 +1,171 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod block;
+mod cmp;
+pub mod codec;
+pub mod container;
+pub mod cursor;
+mod decode;
+#[cfg(feature = "std")]
+pub mod file;
 pub mod get_cache;
+pub mod index;
 pub mod item;
 pub mod iter;
+pub mod memory;
+mod ops;
+mod run;
+#[cfg(feature = "serde")]
+pub mod serde_helpers;
+pub mod set_iter;
+#[cfg(feature = "std")]
+pub mod shared_cache;
+pub mod slice;
+mod stats;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "std")]
 pub mod utils;
 
+use alloc::{vec, vec::Vec};
+use core::{
+    mem::size_of,
+    ops::{Bound, Range, RangeBounds},
+};
+use cursor::Cursor;
 use get_cache::GetCache;
+use index::IndexStrategy;
 use item::Item;
-use iter::{CompSeqIter, CompSeqIterRef};
+use iter::{CompSeqIter, CompSeqIterRef, RangeIter};
+use run::Run;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, mem::size_of, num::NonZeroU16};
+#[cfg(feature = "std")]
+use shared_cache::SharedCache;
 
 /// A compressed sequence of numbers somewhat near to each other
 /// with a frequently occurring step size
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Clone, Default)]
 pub struct CompressedSequence {
     step: u32,
     seq: Vec<Item>,
     index: GetCache,
+    value_index: GetCache,
+    index_strategy: IndexStrategy,
+    /// Amount of items between two index entries or zero if the set isn't indexed
+    index_stride: u32,
 }
 
-impl std::fmt::Debug for CompressedSequence {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let number_items = self.seq.iter().filter(|i| i.is_numbers()).count();
-        let seq_items = self.seq.iter().filter(|i| i.is_sequence()).count();
+/// Serialized fields of a [`CompressedSequence`]. The first three fields have the layout of
+/// the initial release. The index configuration got appended afterwards and is optional when
+/// deserializing so sets written by either version stay readable by formats which aren't self
+/// describing, like bincode
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "CompressedSequence")]
+struct SerializedSequence<S, I> {
+    step: u32,
+    seq: S,
+    index: I,
+    #[serde(default)]
+    index_strategy: Option<IndexStrategy>,
+    #[serde(default)]
+    index_stride: u32,
+}
 
-        let half_numbers = self
-            .seq
-            .iter()
-            .filter(|i| i.is_numbers())
-            .filter(|i| {
-                if let Item::Numbers(_, b) = i {
-                    return b.is_none();
-                }
-                false
-            })
-            .count();
+#[cfg(feature = "serde")]
+const SERIALIZED_FIELDS: &[&str] = &["step", "seq", "index", "index_strategy", "index_stride"];
+
+#[cfg(feature = "serde")]
+impl Serialize for CompressedSequence {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedSequence {
+            step: self.step,
+            seq: &self.seq,
+            index: &self.index,
+            index_strategy: Some(self.index_strategy),
+            index_stride: self.index_stride,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CompressedSequence {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("CompressedSequence", SERIALIZED_FIELDS, SequenceVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct SequenceVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for SequenceVisitor {
+    type Value = CompressedSequence;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("struct CompressedSequence")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        use serde::de::Error;
+
+        let step = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let items = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        let index = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(2, &self))?;
+
+        // Sets written by the initial release end here. Formats which aren't self describing
+        // fail reading past the end instead of returning `None`
+        let index_strategy = seq.next_element().unwrap_or(None).flatten();
+        let index_stride = match index_strategy {
+            Some(_) => seq.next_element()?.unwrap_or(0),
+            None => 0,
+        };
+
+        Ok(CompressedSequence::from_serialized(SerializedSequence {
+            step,
+            seq: items,
+            index,
+            index_strategy,
+            index_stride,
+        }))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+        Ok(CompressedSequence::from_serialized(
+            SerializedSequence::deserialize(deserializer)?,
+        ))
+    }
+}
+
+impl core::fmt::Debug for CompressedSequence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const MIB: f32 = 1024.0 * 1024.0;
+        let stats = self.memory_stats();
 
         f.debug_struct("CompressedSequence")
             .field("steps", &self.step)
             .field("seq_len", &self.seq.len())
-            .field("> numbers", &number_items)
-            .field("> numbers (half)", &half_numbers)
-            .field("> sequences", &seq_items)
-            .field(
-                "> bytes size",
-                &(self.size_of() as f32 / 10240.0f32.powi(2)),
-            )
-            .field(
-                "> raw bytes size",
-                &((self.len() as f32 * 4.0) / 1024.0f32.powi(2)),
-            )
+            .field("> numbers", &stats.number_items)
+            .field("> numbers (half)", &stats.half_number_items)
+            .field("> sequences", &stats.sequence_items)
+            .field("> size (MiB)", &(stats.total_bytes() as f32 / MIB))
+            .field("> raw size (MiB)", &(stats.raw_bytes() as f32 / MIB))
             .finish()
     }
 }
@@ -61,9 +178,44 @@ impl CompressedSequence {
             seq: vec![],
             step,
             index: GetCache::new(),
+            value_index: GetCache::new(),
+            index_strategy: IndexStrategy::default(),
+            index_stride: 0,
+        }
+    }
+
+    /// Creates a set from its items and positional index as stored by earlier versions
+    pub(crate) fn from_parts(step: u32, seq: Vec<Item>, index: GetCache) -> Self {
+        // Keep indexing pushed items at the distance of the stored entries
+        let index_stride = match index.item_stride() {
+            Some(stride) => stride,
+            None if !index.is_empty() => seq.len().max(1) as u32,
+            None => 0,
+        };
+
+        Self {
+            step,
+            seq,
+            index,
+            index_stride,
+            ..Self::default()
         }
     }
 
+    /// Creates a set from its serialized fields. The search indexes get rebuilt if the index
+    /// configuration was serialized, otherwise the stored positional index is kept
+    #[cfg(feature = "serde")]
+    fn from_serialized(fields: SerializedSequence<Vec<Item>, GetCache>) -> Self {
+        let mut set = Self::from_parts(fields.step, fields.seq, fields.index);
+        if let Some(strategy) = fields.index_strategy {
+            set.index_strategy = strategy;
+            set.index_stride = fields.index_stride;
+            set.clear_index();
+            set.extend_index(0);
+        }
+        set
+    }
+
     /// Creates a new compressed sequence from an iterator
     pub fn from_iterator<I>(step: u32, iter: I) -> Self
     where
@@ -81,150 +233,213 @@ impl CompressedSequence {
     /// # Panics
     /// panics if the same item was pushed twice
     pub fn push(&mut self, item: u32) {
-        if self.seq.is_empty() {
-            self.seq.push(Item::new(item));
-            return;
-        }
-
         let step_size = self.step;
+        let added = self
+            .last_item_mut()
+            .is_some_and(|last| last.try_add(item, step_size));
 
-        if !self.seq.last().unwrap().can_add() {
-            self.seq.push(Item::new(item));
-            return;
-        }
-
-        let last_nr = self.last_item().unwrap().last_number(step_size);
-
-        if last_nr + step_size == item {
-            let mut seq = self.seq.pop().unwrap().to_sequence();
-            seq.seq_add();
-            self.seq.push(seq);
-            return;
+        if !added {
+            self.push_new_item(item);
         }
+    }
 
-        let last_item = self.last_item_mut().unwrap();
-        if let Item::Numbers(nr, next) = last_item {
-            assert!(next.is_none());
-            if item == *nr {
-                panic!("Can't push the same value twice");
+    /// Pushes all values of `run`. Runs with the step size of the set extend the last
+    /// sequence item by as many values as fit into it at once
+    pub(crate) fn push_run(&mut self, mut run: Run) {
+        let step = self.step;
+        while let Some(value) = run.pop_front() {
+            self.push(value);
+            if run.is_empty() || (run.len > 1 && run.step != step) {
+                continue;
             }
 
-            if item <= *nr || item - *nr > u16::MAX as u32 {
-                self.seq.push(Item::new(item));
-                return;
+            if let Some(Item::Sequence(start, cnt)) = self.last_item_mut() {
+                let next = *start as u64 + (*cnt as u64 + 1) * step as u64;
+                if next == run.start as u64 {
+                    let n = run.len.min((u16::MAX - *cnt) as u32);
+                    *cnt += n as u16;
+                    run = run.skip(n);
+                }
             }
-
-            *next = Some(NonZeroU16::new((item - *nr) as u16).unwrap());
-            return;
         }
-
-        self.seq.push(Item::new(item));
     }
 
     /// Copies the data to a newly allocated Vec<u32>
     pub fn to_vec(&self) -> Vec<u32> {
         let mut out = vec![];
+        self.decode_into(&mut out);
+        out
+    }
 
-        for item in self.seq.iter() {
-            match item {
-                Item::Numbers(start, next) => {
-                    out.push(*start);
-                    if let Some(next) = next {
-                        out.push(*start + next.get() as u32);
-                    }
-                }
-                Item::Sequence(start, count) => {
-                    for i in 0..*count as u32 + 1 {
-                        out.push(*start + self.step * i);
-                    }
-                }
-            }
-        }
+    /// Returns the step size of the set
+    #[inline]
+    pub fn step(&self) -> u32 {
+        self.step
+    }
 
-        out
+    /// Sets the strategy used by [`update_index`] to build the search indexes
+    ///
+    /// [`update_index`]: CompressedSequence::update_index
+    #[inline]
+    pub fn set_index_strategy(&mut self, strategy: IndexStrategy) {
+        self.index_strategy = strategy;
     }
 
-    /// Updates the search index in the set. The rate is a value between 0% and 100% that indicates
-    /// how much percent of items should be cached
+    /// Returns the strategy used to build the search indexes
+    #[inline]
+    pub fn index_strategy(&self) -> IndexStrategy {
+        self.index_strategy
+    }
+
+    /// Updates the search indexes in the set. The rate is a value between 0% and 100% that
+    /// indicates how much percent of items should be indexed. Items pushed afterwards get
+    /// indexed with the same rate
     pub fn update_index(&mut self, rate: f64) {
-        if self.len() < 5 {
-            return;
-        }
-        let citem_count = ((self.len() as f64 * (rate / 100.0)).ceil() as usize)
-            .min(self.len())
-            .max(1);
-        let stepsize = self.len() / citem_count;
-        let mut index = GetCache::new();
+        self.clear_index();
 
-        let mut pos = 0;
-        for (i, item) in self.seq.iter().enumerate() {
-            if i % stepsize == 0 {
-                index.insert(pos, i as u32, pos);
-            }
+        self.index_stride = if self.index_strategy == IndexStrategy::None {
+            0
+        } else {
+            ((100.0 / rate) as u32).max(1)
+        };
 
-            pos += item.len() as u32;
-        }
+        self.extend_index(0);
+    }
 
-        self.index = index;
+    /// Gets an item at the given position. The cursor remembers the last accessed item which
+    /// makes lookups of nearby positions more efficient
+    pub fn get_cached(&self, pos: usize, cursor: &mut Cursor) -> Option<u32> {
+        let (vec_pos, i_len) = cursor.seek(self, pos)?;
+        self.seq[vec_pos].at(pos - i_len, self.step)
     }
 
-    /// Gets an item at the given position using position cache for more efficient lookups
-    pub fn get_cached(&self, pos: usize, cache: &mut GetCache) -> Option<u32> {
-        let mut item: Option<&Item> = None;
-        let mut i_len = 0;
+    /// Gets an item at the given position using a cache that can be shared between threads
+    /// reading the same set
+    #[cfg(feature = "std")]
+    pub fn get_shared(&self, pos: usize, cache: &SharedCache) -> Option<u32> {
+        let mut cursor = match cache.get(pos) {
+            Some(cursor) => cursor,
+            None => {
+                // Continue from the previous bucket if it's cached. Seeking uses the positional
+                // index otherwise
+                let start = SharedCache::bucket_start(pos);
+                let mut cursor = start
+                    .checked_sub(1)
+                    .and_then(|prev| cache.get(prev))
+                    .unwrap_or_default();
+                cursor.seek(self, start)?;
+                cache.insert(pos, cursor);
+                cursor
+            }
+        };
+
+        let (vec_pos, i_len) = cursor.seek(self, pos)?;
+        self.seq[vec_pos].at(pos - i_len, self.step)
+    }
 
-        let mut vec_pos = 0;
-        let mut skip = 0;
+    /// Gets an item at the given position using position cache for more efficient lookups
+    pub fn get(&self, pos: usize) -> Option<u32> {
+        let (vec_pos, i_len) = self.locate(pos)?;
+        self.seq[vec_pos].at(pos - i_len, self.step)
+    }
 
-        if let Some((_, vec_pos, itemlen)) = cache.get(pos as u32) {
-            item = Some(&self.seq[vec_pos as usize]);
-            i_len = itemlen as usize;
-            skip = vec_pos as usize;
+    /// Moves all values of `other` into `self`, leaving `other` empty. The boundary items
+    /// get merged if the first values of `other` continue the last item of `self`
+    ///
+    /// # Panics
+    /// panics if `other` contains a value that is not greater than the last value of `self`
+    pub fn append(&mut self, other: &mut Self) {
+        let items = core::mem::take(&mut other.seq);
+        other.clear_index();
+
+        if let (Some(last), Some(first)) = (self.last_item(), items.first()) {
+            assert!(
+                last.last_number(self.step) < first.first_number(),
+                "Can't append smaller values"
+            );
         }
 
-        for (p, i) in self.seq.iter().enumerate().skip(skip) {
-            let next_len = i_len + i.len();
-            if pos < next_len {
-                vec_pos = p as u32;
-                item = Some(i);
-                break;
+        if self.step != other.step {
+            // Sequences in `other` are based on a different step size
+            for item in items {
+                self.extend(item.iter(other.step));
             }
-
-            i_len = next_len;
+            return;
         }
 
-        let item = item?;
-        let item_value = item.at(pos - i_len, self.step)?;
-
-        cache.insert(pos as u32, vec_pos, i_len as u32);
+        let mut items = items.into_iter();
+        if let Some(first) = items.next() {
+            self.extend(first.iter(self.step));
+        }
 
-        Some(item_value)
+        let vec_pos = self.seq.len();
+        self.seq.extend(items);
+        self.extend_index(vec_pos);
     }
 
-    /// Gets an item at the given position using position cache for more efficient lookups
-    pub fn get(&self, pos: usize) -> Option<u32> {
-        let mut item: Option<&Item> = None;
-        let mut i_len = 0;
+    /// Splits the set into two at the given value. `self` keeps all values smaller than
+    /// `value` and the returned set contains all others
+    pub fn split_off(&mut self, value: u32) -> Self {
+        let step = self.step;
+        let vec_pos = self.seq.partition_point(|i| i.last_number(step) < value);
+        let offset = self
+            .seq
+            .get(vec_pos)
+            .map(|i| i.lower_bound(value, step))
+            .unwrap_or(0);
+        self.split_items(vec_pos, offset)
+    }
 
-        let mut skip = 0;
+    /// Splits the set into two at the given position. The first set contains all values
+    /// before `pos` and the second one all values starting at `pos`
+    pub fn split_at(mut self, pos: usize) -> (Self, Self) {
+        let other = match self.locate(pos) {
+            Some((vec_pos, i_len)) => self.split_items(vec_pos, pos - i_len),
+            None => Self::new(self.step),
+        };
+        (self, other)
+    }
 
-        if let Some((_, vec_pos, itemlen)) = self.index.get(pos as u32) {
-            item = Some(&self.seq[vec_pos as usize]);
-            i_len = itemlen as usize;
-            skip = vec_pos as usize;
+    /// Retains only the values for which `f` returns `true`. The items get rebuilt in a
+    /// single pass so remaining runs of values stay sequences
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(u32) -> bool,
+    {
+        let step = self.step;
+        let items = core::mem::take(&mut self.seq);
+        self.clear_index();
+
+        for item in items {
+            for value in item.iter(step) {
+                if f(value) {
+                    self.push(value);
+                }
+            }
         }
+    }
 
-        for i in self.seq.iter().skip(skip) {
-            let next_len = i_len + i.len();
-            if pos < next_len {
-                item = Some(i);
-                break;
-            }
+    /// Removes all values outside of `range`. Items only get cut at the bounds of the range
+    /// so all sequences within the range stay intact
+    pub fn filter_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<u32>,
+    {
+        let range = to_u64_range(&range);
 
-            i_len = next_len;
+        if range.end <= u32::MAX as u64 {
+            self.split_off(range.end as u32);
         }
 
-        item?.at(pos - i_len, self.step)
+        if range.start > u32::MAX as u64 {
+            self.seq.clear();
+            self.clear_index();
+        } else if range.start > 0 {
+            let other = self.split_off(range.start as u32);
+            self.seq = other.seq;
+            self.clear_index();
+            self.extend_index(0);
+        }
     }
 
     /// Returns `true` if the set contains the given item using binary search
@@ -242,27 +457,31 @@ impl CompressedSequence {
             }
         }
 
-        let mut size = self.len();
-        let mut left = 0;
-        let mut right = size;
-
-        while left < right {
-            let mid = left + size / 2;
-
-            let cmp = self.get(mid).unwrap().cmp(&item);
+        self.seq
+            .get(self.find_item(item))
+            .is_some_and(|i| i.contains(item, self.step))
+    }
 
-            if cmp == Ordering::Less {
-                left = mid + 1;
-            } else if cmp == Ordering::Greater {
-                right = mid;
-            } else {
-                return true;
+    /// Returns the position of `value` within the set or `None` if the set doesn't contain
+    /// the value. Uses the value index if available
+    pub fn position(&self, value: u32) -> Option<usize> {
+        let (mut vec_pos, mut pos) = self
+            .value_index
+            .get(value)
+            .map(|(_, vec_pos, pos)| (vec_pos as usize, pos as usize))
+            .unwrap_or((0, 0));
+
+        while let Some(item) = self.seq.get(vec_pos) {
+            if item.last_number(self.step) >= value {
+                let offset = item.lower_bound(value, self.step);
+                return (item.at(offset, self.step)? == value).then_some(pos + offset);
             }
 
-            size = right - left;
+            pos += item.len();
+            vec_pos += 1;
         }
 
-        false
+        None
     }
 
     /// Searches the set in linear time for the given `item`
@@ -298,20 +517,67 @@ impl CompressedSequence {
         self.seq.is_empty()
     }
 
-    /// Returns the size of the set in bytes
+    /// Returns the amount of bytes allocated by the set, including unused capacity
     #[inline]
     pub fn size_of(&self) -> usize {
         let size_self = size_of::<Self>();
-        let index_size = size_of::<(u32, u32, u32)>() * self.index.len();
-        size_self + self.seq.len() * size_of::<Item>() + index_size
+        let index_size = self.index.size_of() + self.value_index.size_of();
+        size_self + self.seq.capacity() * size_of::<Item>() + index_size
     }
 
     /// Returns an iterator over all items in the set
     #[inline]
-    pub fn iter(&self) -> CompSeqIterRef {
+    pub fn iter(&self) -> CompSeqIterRef<'_> {
         CompSeqIterRef::new(self)
     }
 
+    /// Returns an iterator over all values within `range`. The first value gets located using
+    /// binary search, so values in front of the range are never decoded
+    pub fn iter_range<R>(&self, range: R) -> RangeIter<'_>
+    where
+        R: RangeBounds<u32>,
+    {
+        let range = to_u64_range(&range);
+        let (vec_pos, ipos) = match u32::try_from(range.start) {
+            Ok(start) => {
+                let vec_pos = self.find_item(start);
+                let ipos = self
+                    .seq
+                    .get(vec_pos)
+                    .map(|i| i.lower_bound(start, self.step))
+                    .unwrap_or(0);
+                (vec_pos, ipos)
+            }
+            Err(_) => (self.seq.len(), 0),
+        };
+
+        let iter = CompSeqIterRef::from_position(&self.seq, self.step, vec_pos, ipos);
+        RangeIter::new(iter, range.end)
+    }
+
+    /// Returns the amount of values within `range`. Values of items are counted arithmetically
+    /// without decoding them
+    pub fn count_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<u32>,
+    {
+        let range = to_u64_range(&range);
+        if range.is_empty() {
+            return 0;
+        }
+
+        let mut count = 0;
+        for item in &self.seq[self.find_item(range.start as u32)..] {
+            let run = Run::from_item(item, self.step);
+            if run.start as u64 >= range.end {
+                break;
+            }
+            count += (run.lower_bound(range.end) - run.lower_bound(range.start)) as usize;
+        }
+
+        count
+    }
+
     #[inline]
     pub(crate) fn seq(&self) -> &Vec<Item> {
         &self.seq
@@ -324,10 +590,97 @@ impl CompressedSequence {
 
     #[inline]
     fn first_value(&self) -> Option<u32> {
-        Some(match self.seq.get(0)? {
-            Item::Numbers(a, _) => *a,
-            Item::Sequence(s, _) => *s,
-        })
+        Some(self.seq.first()?.first_number())
+    }
+
+    /// Returns the position of the item holding the value at `pos` and the amount of
+    /// values stored in all items before it
+    #[inline]
+    fn locate(&self, pos: usize) -> Option<(usize, usize)> {
+        Cursor::new().seek(self, pos)
+    }
+
+    /// Moves all items starting at `vec_pos` into a new set. The item at `vec_pos` gets
+    /// split at `offset` if it isn't zero
+    fn split_items(&mut self, vec_pos: usize, offset: usize) -> Self {
+        let mut other = Self::new(self.step);
+        other.index_strategy = self.index_strategy;
+        other.index_stride = self.index_stride;
+        if vec_pos >= self.seq.len() {
+            return other;
+        }
+
+        other.seq = self.seq.split_off(vec_pos);
+        if offset > 0 {
+            let (left, right) = other.seq[0].split(offset, self.step);
+            self.seq.push(left);
+            other.seq[0] = right;
+        }
+
+        self.index.truncate_items(self.seq.len());
+        self.value_index.truncate_items(self.seq.len());
+        other.extend_index(0);
+        other
+    }
+
+    /// Returns the position of the first item whose last value is not smaller than `value`.
+    /// Uses the value index to narrow down the search if available
+    fn find_item(&self, value: u32) -> usize {
+        let (prev, next) = self.value_index.get_bounds(value);
+        let start = prev.map(|i| i.1 as usize).unwrap_or(0);
+        let end = next.map(|i| i.1 as usize + 1).unwrap_or(self.seq.len());
+
+        start + self.seq[start..end].partition_point(|i| i.last_number(self.step) < value)
+    }
+
+    /// Starts a new item with `value` and adds it to the search indexes if required
+    #[inline]
+    fn push_new_item(&mut self, value: u32) {
+        self.seq.push(Item::new(value));
+        self.extend_index(self.seq.len() - 1);
+    }
+
+    /// Adds all items starting at `vec_pos` to the search indexes. Only every n-th item gets
+    /// indexed, following the configured index rate
+    fn extend_index(&mut self, vec_pos: usize) {
+        let stride = self.index_stride as usize;
+        if stride == 0 {
+            return;
+        }
+
+        // Continue counting values from the last indexed item
+        let (start, mut pos) = [self.index.last(), self.value_index.last()]
+            .into_iter()
+            .flatten()
+            .map(|(_, vec_pos, len)| (vec_pos as usize, len as usize))
+            .filter(|i| i.0 <= vec_pos)
+            .max()
+            .unwrap_or((0, 0));
+        pos += self.seq[start..vec_pos]
+            .iter()
+            .map(|i| i.len())
+            .sum::<usize>();
+
+        for (i, item) in self.seq.iter().enumerate().skip(vec_pos) {
+            if i % stride == 0 {
+                if self.index_strategy.by_position() {
+                    self.index.insert(pos as u32, i as u32, pos as u32);
+                }
+                if self.index_strategy.by_value() {
+                    self.value_index
+                        .insert(item.first_number(), i as u32, pos as u32);
+                }
+            }
+
+            pos += item.len();
+        }
+    }
+
+    /// Removes all entries from the search indexes
+    #[inline]
+    fn clear_index(&mut self) {
+        self.index = GetCache::new();
+        self.value_index = GetCache::new();
     }
 
     #[inline]
@@ -341,6 +694,42 @@ impl CompressedSequence {
     }
 }
 
+/// Returns the most frequent distance between two successive values of a sorted list. The
+/// smallest distance wins ties and lists without two values have a step size of 1
+#[cfg(any(feature = "std", feature = "serde"))]
+pub(crate) fn most_common_step(values: &[u32]) -> u32 {
+    let mut steps: Vec<u32> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    steps.sort_unstable();
+
+    let mut best = (1, 0);
+    for run in steps.chunk_by(|a, b| a == b) {
+        if run.len() > best.1 {
+            best = (run[0], run.len());
+        }
+    }
+    best.0
+}
+
+/// Converts range bounds over `u32` into a half open range that can't overflow
+fn to_u64_range<R>(range: &R) -> Range<u64>
+where
+    R: RangeBounds<u32>,
+{
+    let start = match range.start_bound() {
+        Bound::Included(s) => *s as u64,
+        Bound::Excluded(s) => *s as u64 + 1,
+        Bound::Unbounded => 0,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Included(e) => *e as u64 + 1,
+        Bound::Excluded(e) => *e as u64,
+        Bound::Unbounded => u32::MAX as u64 + 1,
+    };
+
+    start..end.max(start)
+}
+
 impl IntoIterator for CompressedSequence {
     type Item = u32;
 
@@ -372,7 +761,7 @@ mod test {
         let mut exp = vec![];
 
         for j in 1..30u32 {
-            for i in (j * 1..=j * 100).step_by(10) {
+            for i in (j..=j * 100).step_by(10) {
                 comp_seq.push(i);
                 exp.push(i);
             }
@@ -483,4 +872,352 @@ mod test {
         let comp_seq = CompressedSequence::new(10);
         assert_eq!(comp_seq.get(0), None);
     }
+
+    #[test]
+    fn test_append() {
+        let mut comp_seq = CompressedSequence::from_iterator(10, (0..500).step_by(10));
+        let mut other = CompressedSequence::from_iterator(10, (500..1000).step_by(10));
+        other.push(1003);
+
+        comp_seq.append(&mut other);
+
+        let exp: Vec<u32> = (0..1000).step_by(10).chain([1003]).collect();
+        assert!(other.is_empty());
+        assert_eq!(comp_seq.to_vec(), exp);
+        // Both sequences got merged into one
+        assert_eq!(comp_seq.seq().len(), 2);
+
+        let mut other = CompressedSequence::from_iterator(3, (1010..1100).step_by(3));
+        comp_seq.append(&mut other);
+        let exp: Vec<u32> = exp.into_iter().chain((1010..1100).step_by(3)).collect();
+        assert_eq!(comp_seq.to_vec(), exp);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_append_smaller() {
+        let mut comp_seq = CompressedSequence::from_iterator(10, (0..500).step_by(10));
+        let mut other = CompressedSequence::from_iterator(10, (400..1000).step_by(10));
+        comp_seq.append(&mut other);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let exp: Vec<u32> = (0..1000).step_by(10).chain([1003, 1004, 2000]).collect();
+        let comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+
+        for value in [0, 1, 10, 15, 500, 990, 991, 1003, 1004, 1500, 2000, 3000] {
+            let mut left = comp_seq.clone();
+            let right = left.split_off(value);
+
+            let exp_left: Vec<u32> = exp.iter().copied().filter(|i| *i < value).collect();
+            let exp_right: Vec<u32> = exp.iter().copied().filter(|i| *i >= value).collect();
+            assert_eq!(left.to_vec(), exp_left);
+            assert_eq!(right.to_vec(), exp_right);
+        }
+    }
+
+    #[test]
+    fn test_split_at() {
+        let exp: Vec<u32> = (0..1000).step_by(10).chain([1003, 1004, 2000]).collect();
+        let mut comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+        comp_seq.update_index(50.0);
+
+        for pos in [0, 1, 50, 99, 100, 101, 102, 103, 200] {
+            let (left, right) = comp_seq.clone().split_at(pos);
+            let pos = pos.min(exp.len());
+            assert_eq!(left.to_vec(), exp[..pos]);
+            assert_eq!(right.to_vec(), exp[pos..]);
+
+            for (i, v) in exp[..pos].iter().enumerate() {
+                assert_eq!(left.get(i), Some(*v));
+            }
+            assert_eq!(left.get(pos), None);
+        }
+    }
+
+    #[test]
+    fn test_iter_big_sequence() {
+        let comp_seq = CompressedSequence::from_iterator(1, 0..100_000);
+        assert_eq!(comp_seq.iter().count(), 100_000);
+    }
+
+    #[test]
+    fn test_retain() {
+        let exp: Vec<u32> = (0..10_000).step_by(10).chain([10_003, 10_004]).collect();
+        let mut comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+        comp_seq.update_index(50.0);
+
+        let tombstones = [0, 50, 60, 70, 5000, 9990, 10_004];
+        comp_seq.retain(|i| !tombstones.contains(&i));
+
+        let exp: Vec<u32> = exp
+            .into_iter()
+            .filter(|i| !tombstones.contains(i))
+            .collect();
+        assert_eq!(comp_seq.to_vec(), exp);
+        for (pos, i) in exp.iter().enumerate() {
+            assert_eq!(comp_seq.get(pos), Some(*i));
+        }
+
+        comp_seq.retain(|_| false);
+        assert!(comp_seq.is_empty());
+    }
+
+    #[test]
+    fn test_filter_range() {
+        let exp: Vec<u32> = (0..10_000).step_by(10).chain([10_003, 10_004]).collect();
+        let comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+
+        let mut filtered = comp_seq.clone();
+        filtered.filter_range(105..=5000);
+        let filtered_exp: Vec<u32> = exp
+            .iter()
+            .copied()
+            .filter(|i| (105..=5000).contains(i))
+            .collect();
+        assert_eq!(filtered.to_vec(), filtered_exp);
+        assert_eq!(filtered.seq().len(), 1);
+
+        let mut filtered = comp_seq.clone();
+        filtered.filter_range(9995..);
+        assert_eq!(filtered.to_vec(), vec![10_003, 10_004]);
+
+        let mut filtered = comp_seq.clone();
+        filtered.filter_range(..20);
+        assert_eq!(filtered.to_vec(), vec![0, 10]);
+
+        let mut filtered = comp_seq.clone();
+        filtered.filter_range(..);
+        assert_eq!(filtered.to_vec(), exp);
+
+        let mut filtered = comp_seq;
+        filtered.filter_range(20_000..);
+        assert!(filtered.is_empty());
+    }
+
+    #[test]
+    fn test_value_index() {
+        let exp: Vec<u32> = (0..=9120)
+            .step_by(10)
+            .enumerate()
+            .flat_map(|(pos, i)| [i, i + 1].into_iter().take(1 + (pos % 42 == 0) as usize))
+            .collect();
+        let mut comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+
+        for strategy in [
+            IndexStrategy::None,
+            IndexStrategy::Positional,
+            IndexStrategy::Value,
+            IndexStrategy::Both,
+        ] {
+            comp_seq.set_index_strategy(strategy);
+            comp_seq.update_index(20.0);
+
+            assert_eq!(comp_seq.index.is_empty(), !strategy.by_position());
+            assert_eq!(comp_seq.value_index.is_empty(), !strategy.by_value());
+
+            for (pos, i) in exp.iter().enumerate() {
+                assert_eq!(comp_seq.get(pos), Some(*i));
+                assert_eq!(comp_seq.position(*i), Some(pos));
+                assert!(comp_seq.has_bin_search(*i));
+                assert!(!comp_seq.has_bin_search(*i + 2));
+                assert_eq!(comp_seq.position(*i + 2), None);
+            }
+            assert_eq!(comp_seq.position(u32::MAX), None);
+        }
+
+        let mut comp_seq_no_index = comp_seq.clone();
+        comp_seq_no_index.set_index_strategy(IndexStrategy::None);
+        comp_seq_no_index.update_index(20.0);
+        assert!(comp_seq.size_of() > comp_seq_no_index.size_of());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_layout() {
+        // Written by the initial release: values [0, 10, 20, 30, 31, 50, 1000, 1001, 2000]
+        // with step 10 and an index entry for each item
+        let blob: &[u8] = &[
+            10, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 31, 0,
+            0, 0, 1, 19, 0, 0, 0, 0, 0, 232, 3, 0, 0, 1, 1, 0, 0, 0, 0, 0, 208, 7, 0, 0, 0, 4, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 4, 0, 0,
+            0, 6, 0, 0, 0, 2, 0, 0, 0, 6, 0, 0, 0, 8, 0, 0, 0, 3, 0, 0, 0, 8, 0, 0, 0,
+        ];
+        let exp = [0, 10, 20, 30, 31, 50, 1000, 1001, 2000];
+
+        let mut comp_seq: CompressedSequence = bincode::deserialize(blob).unwrap();
+        assert_eq!(comp_seq.to_vec(), exp);
+        assert_eq!(comp_seq.index.len(), 4);
+        assert_eq!(comp_seq.index_stride, 1);
+        for (pos, i) in exp.iter().enumerate() {
+            assert_eq!(comp_seq.get(pos), Some(*i));
+        }
+        // The index configuration gets appended to the layout of the initial release, which
+        // ignores trailing bytes when reading
+        #[derive(Deserialize)]
+        struct Initial {
+            step: u32,
+            seq: Vec<Item>,
+            index: GetCache,
+        }
+        let bytes = bincode::serialize(&comp_seq).unwrap();
+        assert!(bytes.starts_with(blob));
+        let initial: Initial = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(initial.step, comp_seq.step);
+        assert_eq!(initial.seq, comp_seq.seq);
+        assert_eq!(initial.index, comp_seq.index);
+
+        // Pushed items still get indexed
+        comp_seq.push(100_000);
+        assert_eq!(comp_seq.index.len(), 5);
+
+        let json = serde_json::to_string(&comp_seq).unwrap();
+        let comp_seq: CompressedSequence = serde_json::from_str(&json).unwrap();
+        assert_eq!(comp_seq.get(exp.len()), Some(100_000));
+        assert_eq!(comp_seq.index.len(), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_index_strategy() {
+        for strategy in [
+            IndexStrategy::None,
+            IndexStrategy::Positional,
+            IndexStrategy::Value,
+            IndexStrategy::Both,
+        ] {
+            let mut comp_seq = CompressedSequence::from_iterator(10, (0..10_000).step_by(7));
+            comp_seq.set_index_strategy(strategy);
+            comp_seq.update_index(10.0);
+
+            let bytes = bincode::serialize(&comp_seq).unwrap();
+            let json = serde_json::to_string(&comp_seq).unwrap();
+            for mut decoded in [
+                bincode::deserialize::<CompressedSequence>(&bytes).unwrap(),
+                serde_json::from_str(&json).unwrap(),
+            ] {
+                assert_eq!(decoded.to_vec(), comp_seq.to_vec());
+                assert_eq!(decoded.index_strategy, strategy);
+                assert_eq!(decoded.index_stride, comp_seq.index_stride);
+                assert_eq!(decoded.index, comp_seq.index);
+                assert_eq!(decoded.value_index, comp_seq.value_index);
+
+                // Pushed items get indexed the same way
+                let mut exp = comp_seq.clone();
+                exp.extend((10_001..10_300).step_by(3));
+                decoded.extend((10_001..10_300).step_by(3));
+                assert_eq!(decoded.index, exp.index);
+                assert_eq!(decoded.value_index, exp.value_index);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "std", feature = "serde"))]
+    fn test_most_common_step() {
+        assert_eq!(most_common_step(&[]), 1);
+        assert_eq!(most_common_step(&[5]), 1);
+        assert_eq!(most_common_step(&[1, 3, 5, 6, 7, 8, 20]), 1);
+        assert_eq!(most_common_step(&[0, 10, 20, 21, 31]), 10);
+        assert_eq!(most_common_step(&[0, 3, 6, 11, 16]), 3);
+    }
+
+    #[test]
+    fn test_index_push() {
+        let mut comp_seq = CompressedSequence::new(10);
+        comp_seq.set_index_strategy(IndexStrategy::Both);
+        comp_seq.extend((0..100).step_by(10));
+        comp_seq.update_index(10.0);
+
+        let mut exp = comp_seq.to_vec();
+        for (pos, i) in (100..=9120).step_by(10).enumerate() {
+            comp_seq.push(i);
+            exp.push(i);
+
+            if pos % 3 == 0 {
+                comp_seq.push(i + 1);
+                exp.push(i + 1);
+            }
+        }
+
+        let mut rebuilt = comp_seq.clone();
+        rebuilt.update_index(10.0);
+        assert!(comp_seq.index.len() > 10);
+        assert_eq!(comp_seq.index, rebuilt.index);
+        assert_eq!(comp_seq.value_index, rebuilt.value_index);
+
+        for (pos, i) in exp.iter().enumerate() {
+            assert_eq!(comp_seq.get(pos), Some(*i));
+            assert_eq!(comp_seq.position(*i), Some(pos));
+        }
+
+        let (left, right) = comp_seq.split_at(exp.len() / 2);
+        for (pos, i) in exp.iter().enumerate() {
+            if pos < left.len() {
+                assert_eq!(left.get(pos), Some(*i));
+            } else {
+                assert_eq!(right.get(pos - left.len()), Some(*i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        let exp: Vec<u32> = (0..10_000)
+            .step_by(10)
+            .chain([10_003, 10_004, 20_000])
+            .chain((30_000..40_000).step_by(7))
+            .chain([u32::MAX])
+            .collect();
+        let mut comp_seq = CompressedSequence::from_iterator(10, exp.iter().copied());
+        comp_seq.set_index_strategy(IndexStrategy::Value);
+        comp_seq.update_index(10.0);
+
+        let ranges = [
+            (0, 0),
+            (0, 1),
+            (5, 25),
+            (10, 10_004),
+            (9990, 10_005),
+            (10_004, 35_000),
+            (35_001, u32::MAX),
+            (u32::MAX, u32::MAX),
+            (50_000, 40_000),
+        ];
+
+        for (start, end) in ranges {
+            let exp_excl: Vec<u32> = exp
+                .iter()
+                .copied()
+                .filter(|i| (start..end).contains(i))
+                .collect();
+            assert_eq!(
+                comp_seq.iter_range(start..end).collect::<Vec<_>>(),
+                exp_excl
+            );
+            assert_eq!(comp_seq.count_range(start..end), exp_excl.len());
+
+            let exp_incl: Vec<u32> = exp
+                .iter()
+                .copied()
+                .filter(|i| (start..=end).contains(i))
+                .collect();
+            assert_eq!(
+                comp_seq.iter_range(start..=end).collect::<Vec<_>>(),
+                exp_incl
+            );
+            assert_eq!(comp_seq.count_range(start..=end), exp_incl.len());
+        }
+
+        assert_eq!(comp_seq.count_range(..), exp.len());
+        assert_eq!(comp_seq.iter_range(..).count(), exp.len());
+        assert_eq!(comp_seq.count_range(20_000..), 1431);
+        assert_eq!(
+            comp_seq
+                .iter_range((Bound::Excluded(u32::MAX), Bound::Unbounded))
+                .count(),
+            0
+        );
+    }
 }
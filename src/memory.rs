@@ -0,0 +1,125 @@
+use crate::{item::Item, CompressedSequence};
+use core::mem::size_of;
+
+/// Detailed memory usage of a [`CompressedSequence`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MemoryStats {
+    /// Bytes used by the items of the set
+    pub heap_bytes: usize,
+    /// Bytes allocated for the items of the set, including unused capacity
+    pub capacity_bytes: usize,
+    /// Bytes allocated for the search indexes, including unused capacity
+    pub index_bytes: usize,
+    /// Amount of values in the set
+    pub len: usize,
+    /// Amount of number items
+    pub number_items: usize,
+    /// Amount of number items holding a single number only
+    pub half_number_items: usize,
+    /// Amount of sequence items
+    pub sequence_items: usize,
+}
+
+impl MemoryStats {
+    /// Returns the total amount of bytes allocated by the set
+    #[inline]
+    pub fn total_bytes(&self) -> usize {
+        size_of::<CompressedSequence>() + self.capacity_bytes + self.index_bytes
+    }
+
+    /// Returns the amount of bytes the values would take in a plain `Vec<u32>`
+    #[inline]
+    pub fn raw_bytes(&self) -> usize {
+        self.len * size_of::<u32>()
+    }
+
+    /// Returns the ratio between the size of the values in a plain `Vec<u32>` and the total
+    /// size of the set. Values above 1.0 mean the set is smaller
+    #[inline]
+    pub fn compression_ratio(&self) -> f64 {
+        self.raw_bytes() as f64 / self.total_bytes() as f64
+    }
+
+    /// Returns the average amount of values stored in a sequence item
+    pub fn avg_run_length(&self) -> f64 {
+        if self.sequence_items == 0 {
+            return 0.0;
+        }
+
+        let number_values = self.number_items * 2 - self.half_number_items;
+        (self.len - number_values) as f64 / self.sequence_items as f64
+    }
+}
+
+impl CompressedSequence {
+    /// Returns detailed information about the memory used by the set
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            heap_bytes: self.seq.len() * size_of::<Item>(),
+            capacity_bytes: self.seq.capacity() * size_of::<Item>(),
+            index_bytes: self.index.size_of() + self.value_index.size_of(),
+            ..MemoryStats::default()
+        };
+
+        for item in self.seq.iter() {
+            stats.len += item.len();
+            if item.is_sequence() {
+                stats.sequence_items += 1;
+            } else if item.is_numbers() {
+                stats.number_items += 1;
+                if item.len() == 1 {
+                    stats.half_number_items += 1;
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Shrinks the capacity of the items and search indexes as much as possible
+    pub fn shrink_to_fit(&mut self) {
+        self.seq.shrink_to_fit();
+        self.index.shrink_to_fit();
+        self.value_index.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{index::IndexStrategy, CompressedSequence};
+
+    #[test]
+    fn test_memory_stats() {
+        let mut comp_seq = CompressedSequence::new(10);
+        comp_seq.set_index_strategy(IndexStrategy::Both);
+        comp_seq.extend((0..1000).step_by(10));
+        comp_seq.extend([1001, 1005, 1100]);
+        comp_seq.extend((100_000..101_000).step_by(10));
+        comp_seq.update_index(50.0);
+
+        let stats = comp_seq.memory_stats();
+        assert_eq!(stats.len, comp_seq.len());
+        assert_eq!(stats.number_items, 2);
+        assert_eq!(stats.half_number_items, 1);
+        assert_eq!(stats.sequence_items, 2);
+        assert_eq!(stats.avg_run_length(), 100.0);
+        assert_eq!(stats.raw_bytes(), 203 * 4);
+        assert!(stats.capacity_bytes >= stats.heap_bytes);
+        assert!(stats.index_bytes > 0);
+        assert!(stats.compression_ratio() > 1.0);
+        assert_eq!(comp_seq.size_of(), stats.total_bytes());
+
+        comp_seq.shrink_to_fit();
+        let stats = comp_seq.memory_stats();
+        assert_eq!(stats.capacity_bytes, stats.heap_bytes);
+        assert_eq!(comp_seq.size_of(), stats.total_bytes());
+    }
+
+    #[test]
+    fn test_memory_stats_empty() {
+        let stats = CompressedSequence::new(10).memory_stats();
+        assert_eq!(stats.len, 0);
+        assert_eq!(stats.avg_run_length(), 0.0);
+        assert_eq!(stats.compression_ratio(), 0.0);
+    }
+}
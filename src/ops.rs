@@ -0,0 +1,408 @@
+use crate::{item::Item, run::Run, set_iter::RunCursor, CompressedSequence};
+use alloc::{collections::BinaryHeap, vec::Vec};
+use core::cmp::Reverse;
+
+impl CompressedSequence {
+    /// Returns a new set containing all values that are in every set of `sets`. The items of
+    /// all sets are processed as runs: the current runs get intersected as a whole and items
+    /// in front of the other sets next values are skipped using galloping search. The result
+    /// uses the step size of the smallest set
+    pub fn intersect_all(sets: &[&CompressedSequence]) -> CompressedSequence {
+        let mut sets: Vec<_> = sets.iter().map(|i| (i.len(), *i)).collect();
+        sets.sort_by_key(|i| i.0);
+
+        let Some((_, smallest)) = sets.first() else {
+            return CompressedSequence::default();
+        };
+
+        let mut out = CompressedSequence::new(smallest.step);
+        let mut cursors: Vec<_> = sets.iter().map(|(_, set)| RunCursor::new(set)).collect();
+
+        loop {
+            // Skip all values in front of the latest start of the current runs
+            let mut start = 0;
+            for cursor in cursors.iter_mut() {
+                cursor.seek(start as u64);
+                let Some(run) = cursor.current() else {
+                    return out;
+                };
+                start = start.max(run.start);
+            }
+
+            // Values up to the first end of the current runs can only be in the current runs
+            let mut run = cursors[0].current().unwrap();
+            let mut end = run.last();
+            for cursor in cursors[1..].iter() {
+                let other = cursor.current().unwrap();
+                run = run.intersect(&other);
+                end = end.min(other.last());
+            }
+
+            out.push_run(run);
+            for cursor in cursors.iter_mut() {
+                cursor.seek(end as u64 + 1);
+            }
+        }
+    }
+
+    /// Returns a new set with the given step size containing all values of `sets`. The items of
+    /// all sets are merged as runs: a heap keyed by the start of each sets current run yields
+    /// the run with the smallest start, whose values in front of the next start of any other set
+    /// get pushed at once
+    pub fn union_all(sets: &[&CompressedSequence], step: u32) -> CompressedSequence {
+        let mut cursors: Vec<_> = sets.iter().map(|set| RunCursor::new(set)).collect();
+        let mut heap: BinaryHeap<_> = cursors
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, cursor)| Some(Reverse((cursor.current()?.start, pos))))
+            .collect();
+        let mut out = CompressedSequence::new(step);
+
+        while let Some(Reverse((start, first))) = heap.pop() {
+            let run = cursors[first].current().unwrap();
+
+            // Take at least the first value, which consumes it from all sets starting with it
+            let run = match heap.peek() {
+                Some(Reverse((next, _))) => run.take(run.lower_bound(*next as u64).max(1)),
+                None => run,
+            };
+            out.push_run(run);
+
+            // Advance all sets whose current run starts within the pushed values
+            let end = run.last() as u64 + 1;
+            heap.push(Reverse((start, first)));
+            while let Some(Reverse((start, pos))) = heap.peek().copied() {
+                if start as u64 >= end {
+                    break;
+                }
+
+                heap.pop();
+                cursors[pos].seek(end);
+                if let Some(run) = cursors[pos].current() {
+                    heap.push(Reverse((run.start, pos)));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Returns the amount of values that are in `self` and in `other` without building the
+    /// intersection. The overlap of two items is computed in closed form
+    pub fn intersection_len(&self, other: &CompressedSequence) -> usize {
+        let (mut i, mut j) = (0, 0);
+        let mut len = 0;
+
+        while let (Some(a), Some(b)) = (self.seq.get(i), other.seq.get(j)) {
+            let a = Run::from_item(a, self.step);
+            let b = Run::from_item(b, other.step);
+
+            if a.last() < b.start {
+                i = gallop(&self.seq, i + 1, b.start, self.step);
+                continue;
+            }
+            if b.last() < a.start {
+                j = gallop(&other.seq, j + 1, a.start, other.step);
+                continue;
+            }
+
+            len += a.intersect(&b).len as usize;
+
+            if a.last() <= b.last() {
+                i += 1;
+            }
+            if b.last() <= a.last() {
+                j += 1;
+            }
+        }
+
+        len
+    }
+
+    /// Returns the amount of values that are in `self` or in `other` without building the union
+    #[inline]
+    pub fn union_len(&self, other: &CompressedSequence) -> usize {
+        self.len() + other.len() - self.intersection_len(other)
+    }
+
+    /// Returns the amount of values that are in `self` but not in `other` without building the
+    /// difference
+    #[inline]
+    pub fn difference_len(&self, other: &CompressedSequence) -> usize {
+        self.len() - self.intersection_len(other)
+    }
+
+    /// Returns the jaccard similarity of both sets which is the size of the intersection divided
+    /// by the size of the union. Two empty sets are considered equal and have a similarity of 1.0
+    pub fn jaccard(&self, other: &CompressedSequence) -> f64 {
+        let intersection = self.intersection_len(other);
+        let union = self.len() + other.len() - intersection;
+        if union == 0 {
+            return 1.0;
+        }
+        intersection as f64 / union as f64
+    }
+
+    /// Returns `true` if every value of `self` is also in `other`. Items are compared as runs
+    /// and the check stops at the first value that is missing in `other`
+    pub fn is_subset(&self, other: &CompressedSequence) -> bool {
+        let mut j = 0;
+
+        for item in self.seq.iter() {
+            let mut a = Run::from_item(item, self.step);
+
+            while !a.is_empty() {
+                j = gallop(&other.seq, j, a.start, other.step);
+                let Some(b) = other.seq.get(j) else {
+                    return false;
+                };
+                let b = Run::from_item(b, other.step);
+
+                // Values of `a` that have to be covered by `b`
+                let covered = a.lower_bound(b.last() as u64 + 1);
+                if !b.contains_run(&a.take(covered)) {
+                    return false;
+                }
+                a = a.skip(covered);
+            }
+        }
+
+        true
+    }
+
+    /// Returns `true` if every value of `other` is also in `self`
+    #[inline]
+    pub fn is_superset(&self, other: &CompressedSequence) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if `self` and `other` have no values in common. The check stops at the
+    /// first pair of overlapping items which share a value
+    pub fn is_disjoint(&self, other: &CompressedSequence) -> bool {
+        let (mut i, mut j) = (0, 0);
+
+        while let (Some(a), Some(b)) = (self.seq.get(i), other.seq.get(j)) {
+            let a = Run::from_item(a, self.step);
+            let b = Run::from_item(b, other.step);
+
+            if a.last() < b.start {
+                i = gallop(&self.seq, i + 1, b.start, self.step);
+                continue;
+            }
+            if b.last() < a.start {
+                j = gallop(&other.seq, j + 1, a.start, other.step);
+                continue;
+            }
+
+            if !a.intersect(&b).is_empty() {
+                return false;
+            }
+
+            if a.last() <= b.last() {
+                i += 1;
+            }
+            if b.last() <= a.last() {
+                j += 1;
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns the position of the first item at or after `from` whose last value is not smaller
+/// than `value` using galloping search
+pub(crate) fn gallop(items: &[Item], from: usize, value: u32, step: u32) -> usize {
+    let mut lo = from;
+    let mut bound = 1;
+    while lo + bound < items.len() && items[lo + bound].last_number(step) < value {
+        lo += bound;
+        bound *= 2;
+    }
+
+    let hi = (lo + bound + 1).min(items.len());
+    if lo >= hi {
+        return items.len();
+    }
+    lo + items[lo..hi].partition_point(|i| i.last_number(step) < value)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::CompressedSequence;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    /// Creates random sets with runs of different step sizes and some outliers
+    fn make_sets(count: usize) -> Vec<BTreeSet<u32>> {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        (0..count)
+            .map(|_| {
+                let mut set = BTreeSet::new();
+                for _ in 0..rng.gen_range(1..20) {
+                    let start = rng.gen_range(0..50_000);
+                    let step = [3, 5, 10][rng.gen_range(0..3)];
+                    let len = rng.gen_range(1..2000);
+                    set.extend((start..start + step * len).step_by(step as usize));
+                }
+                for _ in 0..rng.gen_range(0..500) {
+                    set.insert(rng.gen_range(0..60_000));
+                }
+                set
+            })
+            .collect()
+    }
+
+    fn to_comp_seqs(sets: &[BTreeSet<u32>]) -> Vec<CompressedSequence> {
+        sets.iter()
+            .enumerate()
+            .map(|(pos, i)| {
+                CompressedSequence::from_iterator([3, 5, 10][pos % 3], i.iter().copied())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_intersect_all() {
+        let sets = make_sets(6);
+        let comp_seqs = to_comp_seqs(&sets);
+
+        for count in 1..=sets.len() {
+            let refs: Vec<_> = comp_seqs[..count].iter().collect();
+            let exp: Vec<u32> = sets[0]
+                .iter()
+                .copied()
+                .filter(|i| sets[1..count].iter().all(|s| s.contains(i)))
+                .collect();
+
+            assert_eq!(CompressedSequence::intersect_all(&refs).to_vec(), exp);
+        }
+
+        assert!(CompressedSequence::intersect_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_intersect_all_disjoint() {
+        let a = CompressedSequence::from_iterator(10, (0..1000).step_by(10));
+        let b = CompressedSequence::from_iterator(10, (5..1000).step_by(10));
+        let c = CompressedSequence::new(10);
+        assert!(CompressedSequence::intersect_all(&[&a, &b]).is_empty());
+        assert!(CompressedSequence::intersect_all(&[&a, &c]).is_empty());
+    }
+
+    #[test]
+    fn test_union_all() {
+        let sets = make_sets(6);
+        let comp_seqs = to_comp_seqs(&sets);
+
+        for count in 0..=sets.len() {
+            let refs: Vec<_> = comp_seqs[..count].iter().collect();
+            let exp: BTreeSet<u32> = sets[..count].iter().flatten().copied().collect();
+
+            let union = CompressedSequence::union_all(&refs, 5);
+            assert_eq!(union.step(), 5);
+            assert_eq!(union.to_vec(), exp.into_iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_all_overlapping_runs() {
+        let a = CompressedSequence::from_iterator(2, (0..=u32::MAX).rev().step_by(2).take(200_000));
+        let b = CompressedSequence::from_iterator(3, (0..=u32::MAX).rev().step_by(3).take(150_000));
+        let c = CompressedSequence::from_iterator(1, (u32::MAX - 100_000..=u32::MAX).chain([7]));
+        let sets = [&a, &b, &c];
+
+        let exp: Vec<u32> = (u32::MAX - 100_000..=u32::MAX)
+            .filter(|i| (u32::MAX - i) % 6 == 0)
+            .collect();
+        let intersection = CompressedSequence::intersect_all(&sets);
+        assert_eq!(intersection.to_vec(), exp);
+
+        let exp: BTreeSet<u32> = sets.iter().flat_map(|i| i.iter()).collect();
+        for step in [1, 2, 3] {
+            let union = CompressedSequence::union_all(&sets, step);
+            assert!(union.iter().eq(exp.iter().copied()));
+        }
+
+        // Whole runs with the output step size end up in as few items as pushing every value
+        let union = CompressedSequence::union_all(&[&a], 2);
+        assert_eq!(union.seq(), a.seq());
+    }
+
+    #[test]
+    fn test_cardinality() {
+        let sets = make_sets(6);
+        let comp_seqs = to_comp_seqs(&sets);
+
+        for (a, seq_a) in sets.iter().zip(comp_seqs.iter()) {
+            for (b, seq_b) in sets.iter().zip(comp_seqs.iter()) {
+                let intersection = a.intersection(b).count();
+                let union = a.union(b).count();
+
+                assert_eq!(seq_a.intersection_len(seq_b), intersection);
+                assert_eq!(seq_a.union_len(seq_b), union);
+                assert_eq!(seq_a.difference_len(seq_b), a.difference(b).count());
+                assert_eq!(seq_a.jaccard(seq_b), intersection as f64 / union as f64);
+            }
+        }
+
+        let empty = CompressedSequence::new(10);
+        assert_eq!(empty.jaccard(&empty), 1.0);
+        assert_eq!(comp_seqs[0].jaccard(&empty), 0.0);
+        assert_eq!(comp_seqs[0].intersection_len(&empty), 0);
+    }
+
+    #[test]
+    fn test_subset_disjoint() {
+        let sets = make_sets(6);
+        let comp_seqs = to_comp_seqs(&sets);
+
+        for (a, seq_a) in sets.iter().zip(comp_seqs.iter()) {
+            // Subsets keeping whole runs, single values of runs and ranges
+            let subsets: Vec<BTreeSet<u32>> = vec![
+                a.iter().copied().filter(|i| i % 2 == 0).collect(),
+                a.iter().copied().filter(|i| i % 30 == 0).collect(),
+                a.range(10_000..20_000).copied().collect(),
+                a.iter().copied().step_by(7).collect(),
+                BTreeSet::new(),
+            ];
+            let others = sets.iter().chain(subsets.iter());
+
+            for (pos, b) in others.enumerate() {
+                let seq_b =
+                    CompressedSequence::from_iterator([3, 5, 10][pos % 3], b.iter().copied());
+
+                assert_eq!(seq_b.is_subset(seq_a), b.is_subset(a));
+                assert_eq!(seq_a.is_subset(&seq_b), a.is_subset(b));
+                assert_eq!(seq_a.is_superset(&seq_b), a.is_superset(b));
+                assert_eq!(seq_a.is_disjoint(&seq_b), a.is_disjoint(b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_subset_runs() {
+        let a = CompressedSequence::from_iterator(2, (0..1_000_000).step_by(2));
+        let b = CompressedSequence::from_iterator(6, (600..900_000).step_by(6));
+        let c = CompressedSequence::from_iterator(3, (0..1_000_000).step_by(3));
+        assert!(b.is_subset(&a) && b.is_subset(&c));
+        assert!(!a.is_subset(&b) && !c.is_subset(&a));
+        assert!(a.is_superset(&b));
+
+        let odd = CompressedSequence::from_iterator(2, (1..1_000_000).step_by(2));
+        assert!(a.is_disjoint(&odd) && !a.is_disjoint(&c));
+
+        let empty = CompressedSequence::new(1);
+        assert!(empty.is_subset(&a) && empty.is_subset(&empty));
+        assert!(!a.is_subset(&empty) && a.is_disjoint(&empty));
+    }
+
+    #[test]
+    fn test_cardinality_runs() {
+        let a = CompressedSequence::from_iterator(2, (0..1_000_000).step_by(2));
+        let b = CompressedSequence::from_iterator(3, (0..1_000_000).step_by(3));
+        assert_eq!(a.intersection_len(&b), (0..1_000_000).step_by(6).count());
+        assert_eq!(a.difference_len(&b), 500_000 - 166_667);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::item::Item;
+
+/// Arithmetic progression of `len` values starting at `start`. Every item of a set can be
+/// represented as a run which allows computing set operations in closed form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Run {
+    pub start: u32,
+    pub step: u32,
+    pub len: u32,
+}
+
+impl Run {
+    #[inline]
+    pub fn new(start: u32, step: u32, len: u32) -> Self {
+        // The step size doesn't matter for runs with a single value
+        let step = if len > 1 { step.max(1) } else { 1 };
+        Self { start, step, len }
+    }
+
+    /// Creates a run holding all values of `item`
+    #[inline]
+    pub fn from_item(item: &Item, step: u32) -> Self {
+        match item {
+            Item::Numbers(a, None) => Self::new(*a, 1, 1),
+            Item::Numbers(a, Some(b)) => Self::new(*a, b.get() as u32, 2),
+            Item::Sequence(start, cnt) => Self::new(*start, step, *cnt as u32 + 1),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the last value of the run. Must not be called on empty runs
+    #[inline]
+    pub fn last(&self) -> u32 {
+        self.start + (self.len - 1) * self.step
+    }
+
+    /// Returns `true` if the run contains `value`
+    #[inline]
+    pub fn contains(&self, value: u32) -> bool {
+        !self.is_empty()
+            && value >= self.start
+            && value <= self.last()
+            && (value - self.start).is_multiple_of(self.step)
+    }
+
+    /// Returns `true` if all values of `other` are in the run. This is the case if both ends of
+    /// `other` are in the run and its step size is a multiple of the runs step size
+    #[inline]
+    pub fn contains_run(&self, other: &Run) -> bool {
+        other.is_empty()
+            || (self.contains(other.start)
+                && self.contains(other.last())
+                && (other.len == 1 || other.step.is_multiple_of(self.step)))
+    }
+
+    /// Returns the position of the first value that is not smaller than `value` or the length
+    /// of the run if all values are smaller
+    #[inline]
+    pub fn lower_bound(&self, value: u64) -> u32 {
+        if value <= self.start as u64 {
+            return 0;
+        }
+        let step = self.step as u64;
+        ((value - self.start as u64).div_ceil(step)).min(self.len as u64) as u32
+    }
+
+    /// Returns the run without its first `n` values
+    #[inline]
+    pub fn skip(&self, n: u32) -> Self {
+        if n >= self.len {
+            return Self::default();
+        }
+        Self::new(self.start + n * self.step, self.step, self.len - n)
+    }
+
+    /// Returns the first `n` values of the run
+    #[inline]
+    pub fn take(&self, n: u32) -> Self {
+        Self::new(self.start, self.step, n.min(self.len))
+    }
+
+    /// Removes the first value from the run and returns it
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        let value = self.start;
+        *self = self.skip(1);
+        Some(value)
+    }
+
+    /// Returns the run of all values contained in both runs
+    pub fn intersect(&self, other: &Run) -> Run {
+        if self.is_empty() || other.is_empty() {
+            return Run::default();
+        }
+
+        let lo = self.start.max(other.start) as i128;
+        let hi = self.last().min(other.last()) as i128;
+        if lo > hi {
+            return Run::default();
+        }
+
+        let (s1, d1) = (self.start as i128, self.step as i128);
+        let (s2, d2) = (other.start as i128, other.step as i128);
+
+        // Solve x = s1 (mod d1) and x = s2 (mod d2)
+        let (g, p, _) = ext_gcd(d1, d2);
+        if (s2 - s1) % g != 0 {
+            return Run::default();
+        }
+        let lcm = d1 / g * d2;
+        let m = d2 / g;
+        let k = ((s2 - s1) / g * p).rem_euclid(m);
+        let x = s1 + d1 * k;
+
+        // First solution not smaller than `lo`. `x` is smaller than `s1 + lcm` so there is
+        // no smaller solution above `lo`
+        let first = x + ((lo - x).max(0) + lcm - 1) / lcm * lcm;
+        if first > hi {
+            return Run::default();
+        }
+
+        let len = (hi - first) / lcm + 1;
+        Run::new(first as u32, lcm.min(u32::MAX as i128) as u32, len as u32)
+    }
+}
+
+/// Returns (gcd, x, y) with a * x + b * y = gcd
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = ext_gcd(b, a % b);
+    (g, y, x - a / b * y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Run;
+
+    fn values(run: Run) -> Vec<u32> {
+        (0..run.len).map(|i| run.start + i * run.step).collect()
+    }
+
+    #[test]
+    fn test_run_intersect() {
+        let mut runs = vec![];
+        for start in [0, 1, 3, 7, 12] {
+            for step in [1, 2, 3, 4, 6, 7] {
+                for len in [1, 2, 5, 17] {
+                    runs.push(Run::new(start, step, len));
+                }
+            }
+        }
+
+        for a in runs.iter() {
+            for b in runs.iter() {
+                let b_values = values(*b);
+                let exp: Vec<u32> = values(*a)
+                    .into_iter()
+                    .filter(|i| b_values.contains(i))
+                    .collect();
+                let run = a.intersect(b);
+                assert_eq!(values(run), exp, "{a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_contains() {
+        let runs: Vec<Run> = [
+            (0, 1, 5),
+            (2, 2, 4),
+            (4, 4, 2),
+            (3, 3, 3),
+            (6, 1, 1),
+            (5, 9, 0),
+        ]
+        .into_iter()
+        .map(|(start, step, len)| Run::new(start, step, len))
+        .collect();
+
+        for a in runs.iter() {
+            for value in 0..20 {
+                assert_eq!(a.contains(value), values(*a).contains(&value));
+            }
+            for b in runs.iter() {
+                let exp = values(*b).iter().all(|i| values(*a).contains(i));
+                assert_eq!(a.contains_run(b), exp, "{a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_bounds() {
+        let run = Run::new(10, 5, 4);
+        assert_eq!(run.last(), 25);
+        assert_eq!(run.lower_bound(0), 0);
+        assert_eq!(run.lower_bound(11), 1);
+        assert_eq!(run.lower_bound(15), 1);
+        assert_eq!(run.lower_bound(26), 4);
+        assert_eq!(values(run.skip(3)), vec![25]);
+        assert_eq!(values(run.take(2)), vec![10, 15]);
+        assert!(run.skip(4).is_empty());
+
+        let big = Run::new(u32::MAX - 10, 10, 2);
+        assert_eq!(big.last(), u32::MAX);
+        assert_eq!(values(big), vec![u32::MAX - 10, u32::MAX]);
+        assert_eq!(big.intersect(&Run::new(u32::MAX, 1, 1)).len, 1);
+    }
+}
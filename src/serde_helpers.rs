@@ -0,0 +1,124 @@
+use crate::CompressedSequence;
+use alloc::vec::Vec;
+
+/// Serializes a set as plain sorted array of its values. The step size of deserialized sets
+/// is the most frequent distance between two successive values
+///
+/// ```
+/// use compressed_set::CompressedSequence;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Document {
+///     #[serde(with = "compressed_set::serde_helpers::sorted_array")]
+///     readable: CompressedSequence,
+///     #[serde(with = "compressed_set::serde_helpers::compact_bytes")]
+///     compact: CompressedSequence,
+/// }
+/// ```
+pub mod sorted_array {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(set: &CompressedSequence, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(set.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<CompressedSequence, D::Error> {
+        let mut values = Vec::<u32>::deserialize(d)?;
+        values.sort_unstable();
+        values.dedup();
+        Ok(CompressedSequence::from_iterator(
+            crate::most_common_step(&values),
+            values,
+        ))
+    }
+}
+
+/// Serializes a set as bytes using the format of [`CompressedSequence::to_bytes`]
+pub mod compact_bytes {
+    use super::*;
+    use core::fmt;
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserializer, Serializer,
+    };
+
+    pub fn serialize<S: Serializer>(set: &CompressedSequence, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(&set.to_bytes())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<CompressedSequence, D::Error> {
+        d.deserialize_bytes(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = CompressedSequence;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an encoded compressed sequence")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            CompressedSequence::from_bytes(v).map_err(E::custom)
+        }
+
+        // Self describing formats like JSON represent bytes as sequence
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(byte) = seq.next_element::<u8>()? {
+                bytes.push(byte);
+            }
+            self.visit_bytes(&bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        #[serde(with = "crate::serde_helpers::sorted_array")]
+        array: CompressedSequence,
+        #[serde(with = "crate::serde_helpers::compact_bytes")]
+        bytes: CompressedSequence,
+    }
+
+    #[test]
+    fn test_with_helpers() {
+        let set = CompressedSequence::from_iterator(3, [1, 4, 7, 10, 20, 100, 103, 106]);
+        let fields = Fields {
+            array: set.clone(),
+            bytes: set.clone(),
+        };
+
+        let json = serde_json::to_string(&fields).unwrap();
+        assert!(json.starts_with(r#"{"array":[1,4,7,10,20,100,103,106],"bytes":["#));
+
+        let decoded: Fields = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.array.to_vec(), set.to_vec());
+        assert_eq!(decoded.array.step(), 3);
+        assert_eq!(decoded.bytes.to_vec(), set.to_vec());
+        assert_eq!(decoded.bytes.step(), 3);
+    }
+
+    #[test]
+    fn test_sorted_array_unsorted() {
+        let json = r#"{"array":[10,2,4,2,6,8],"bytes":[1,0]}"#;
+        let decoded: Fields = serde_json::from_str(json).unwrap();
+        assert_eq!(decoded.array.to_vec(), vec![2, 4, 6, 8, 10]);
+        assert_eq!(decoded.array.step(), 2);
+        assert!(decoded.bytes.is_empty());
+    }
+
+    #[test]
+    fn test_compact_bytes_invalid() {
+        let json = r#"{"array":[],"bytes":[1,1,3]}"#;
+        assert!(serde_json::from_str::<Fields>(json).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{item::Item, ops::gallop, run::Run, CompressedSequence};
+
+/// Lazy iterator over the values in both of two sets.
+/// Created by [`CompressedSequence::intersect_iter`]
+pub struct Intersection<'a> {
+    a: RunCursor<'a>,
+    b: RunCursor<'a>,
+    pending: Run,
+}
+
+/// Lazy iterator over the values in any of two sets.
+/// Created by [`CompressedSequence::union_iter`]
+pub struct Union<'a> {
+    a: RunCursor<'a>,
+    b: RunCursor<'a>,
+    pending: Run,
+}
+
+/// Lazy iterator over the values in the first but not in the second set.
+/// Created by [`CompressedSequence::difference_iter`]
+pub struct Difference<'a> {
+    a: RunCursor<'a>,
+    b: RunCursor<'a>,
+    pending: Run,
+}
+
+impl CompressedSequence {
+    /// Returns a lazy iterator over all values that are in `self` and in `other`. Items that
+    /// don't overlap get skipped and overlapping items are intersected as a whole
+    ///
+    /// The result can be streamed into another set using [`Extend`]:
+    /// ```
+    /// # use compressed_set::CompressedSequence;
+    /// let a = CompressedSequence::from_iterator(2, (0..100).step_by(2));
+    /// let b = CompressedSequence::from_iterator(3, (0..100).step_by(3));
+    ///
+    /// let mut out = CompressedSequence::new(6);
+    /// out.extend(a.intersect_iter(&b));
+    /// assert_eq!(out.to_vec(), (0..100).step_by(6).collect::<Vec<_>>());
+    /// ```
+    #[inline]
+    pub fn intersect_iter<'a>(&'a self, other: &'a CompressedSequence) -> Intersection<'a> {
+        Intersection {
+            a: RunCursor::new(self),
+            b: RunCursor::new(other),
+            pending: Run::default(),
+        }
+    }
+
+    /// Returns a lazy iterator over all values that are in `self` or in `other`. Values in
+    /// front of the other sets next value are emitted as whole runs
+    #[inline]
+    pub fn union_iter<'a>(&'a self, other: &'a CompressedSequence) -> Union<'a> {
+        Union {
+            a: RunCursor::new(self),
+            b: RunCursor::new(other),
+            pending: Run::default(),
+        }
+    }
+
+    /// Returns a lazy iterator over all values that are in `self` but not in `other`. Items of
+    /// `other` that don't overlap get skipped and values of `self` in front of the next value
+    /// of `other` are emitted as whole runs
+    #[inline]
+    pub fn difference_iter<'a>(&'a self, other: &'a CompressedSequence) -> Difference<'a> {
+        Difference {
+            a: RunCursor::new(self),
+            b: RunCursor::new(other),
+            pending: Run::default(),
+        }
+    }
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.pending.pop_front() {
+                return Some(value);
+            }
+
+            let (a, b) = (self.a.current()?, self.b.current()?);
+            if a.last() < b.start {
+                self.a.seek(b.start as u64);
+                continue;
+            }
+            if b.last() < a.start {
+                self.b.seek(a.start as u64);
+                continue;
+            }
+
+            self.pending = a.intersect(&b);
+
+            let end = a.last().min(b.last()) as u64 + 1;
+            self.a.seek(end);
+            self.b.seek(end);
+        }
+    }
+}
+
+impl<'a> Iterator for Union<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.pending.pop_front() {
+                return Some(value);
+            }
+
+            let (first, second) = match (self.a.current(), self.b.current()) {
+                (None, None) => return None,
+                (Some(_), None) => (&mut self.a, &mut self.b),
+                (None, Some(_)) => (&mut self.b, &mut self.a),
+                (Some(a), Some(b)) if a.start <= b.start => (&mut self.a, &mut self.b),
+                (Some(_), Some(_)) => (&mut self.b, &mut self.a),
+            };
+
+            let run = first.current().unwrap();
+            let n = match second.current() {
+                Some(other) if other.start == run.start => {
+                    second.consume(1);
+                    1
+                }
+                Some(other) => run.lower_bound(other.start as u64),
+                None => run.len,
+            };
+
+            self.pending = run.take(n);
+            first.consume(n);
+        }
+    }
+}
+
+impl<'a> Iterator for Difference<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.pending.pop_front() {
+                return Some(value);
+            }
+
+            let a = self.a.current()?;
+            self.b.seek(a.start as u64);
+
+            match self.b.current() {
+                Some(b) if b.start == a.start => {
+                    self.a.consume(1);
+                    self.b.consume(1);
+                }
+                Some(b) => {
+                    let n = a.lower_bound(b.start as u64);
+                    self.pending = a.take(n);
+                    self.a.consume(n);
+                }
+                None => {
+                    self.pending = a;
+                    self.a.consume(a.len);
+                }
+            }
+        }
+    }
+}
+
+/// Cursor over the items of a set, holding the not yet consumed values of the current item
+pub(crate) struct RunCursor<'a> {
+    items: &'a [Item],
+    step: u32,
+    pos: usize,
+    run: Run,
+}
+
+impl<'a> RunCursor<'a> {
+    #[inline]
+    pub(crate) fn new(set: &'a CompressedSequence) -> Self {
+        let mut cursor = Self {
+            items: &set.seq,
+            step: set.step,
+            pos: 0,
+            run: Run::default(),
+        };
+        cursor.load(0);
+        cursor
+    }
+
+    /// Returns the remaining values of the current item or `None` if all values are consumed
+    #[inline]
+    pub(crate) fn current(&self) -> Option<Run> {
+        (!self.run.is_empty()).then_some(self.run)
+    }
+
+    /// Consumes the next `n` values of the current item
+    #[inline]
+    fn consume(&mut self, n: u32) {
+        self.run = self.run.skip(n);
+        if self.run.is_empty() {
+            self.load(self.pos + 1);
+        }
+    }
+
+    /// Consumes all values smaller than `value`, skipping whole items if possible
+    pub(crate) fn seek(&mut self, value: u64) {
+        if self.run.is_empty() {
+            return;
+        }
+
+        if (self.run.last() as u64) < value {
+            let pos = if value > u32::MAX as u64 {
+                self.items.len()
+            } else {
+                gallop(self.items, self.pos + 1, value as u32, self.step)
+            };
+            self.load(pos);
+            if self.run.is_empty() {
+                return;
+            }
+        }
+
+        self.run = self.run.skip(self.run.lower_bound(value));
+    }
+
+    #[inline]
+    fn load(&mut self, pos: usize) {
+        self.pos = pos;
+        self.run = self
+            .items
+            .get(pos)
+            .map(|i| Run::from_item(i, self.step))
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::CompressedSequence;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    fn make_pairs() -> Vec<(BTreeSet<u32>, BTreeSet<u32>)> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let rand_set = |rng: &mut StdRng| {
+            let mut set = BTreeSet::new();
+            for _ in 0..rng.gen_range(0..10) {
+                let start = rng.gen_range(0..5_000);
+                let step = rng.gen_range(1..8);
+                set.extend((start..start + step * rng.gen_range(1..500)).step_by(step as usize));
+            }
+            for _ in 0..rng.gen_range(0..100) {
+                set.insert(rng.gen_range(0..6_000));
+            }
+            set
+        };
+
+        let mut pairs: Vec<_> = (0..30)
+            .map(|_| (rand_set(&mut rng), rand_set(&mut rng)))
+            .collect();
+        pairs.push((BTreeSet::new(), BTreeSet::new()));
+        pairs.push(((0..1000).collect(), BTreeSet::new()));
+        pairs.push(((0..1000).collect(), (0..1000).step_by(3).collect()));
+        pairs.push(([0, u32::MAX].into(), [u32::MAX - 1, u32::MAX].into()));
+        pairs
+    }
+
+    fn to_seq(set: &BTreeSet<u32>, step: u32) -> CompressedSequence {
+        CompressedSequence::from_iterator(step, set.iter().copied())
+    }
+
+    #[test]
+    fn test_set_iters() {
+        for (a, b) in make_pairs() {
+            for (step_a, step_b) in [(1, 1), (2, 3), (5, 5)] {
+                let (seq_a, seq_b) = (to_seq(&a, step_a), to_seq(&b, step_b));
+
+                let exp: Vec<_> = a.intersection(&b).copied().collect();
+                assert_eq!(seq_a.intersect_iter(&seq_b).collect::<Vec<_>>(), exp);
+
+                let exp: Vec<_> = a.union(&b).copied().collect();
+                assert_eq!(seq_a.union_iter(&seq_b).collect::<Vec<_>>(), exp);
+
+                let exp: Vec<_> = a.difference(&b).copied().collect();
+                assert_eq!(seq_a.difference_iter(&seq_b).collect::<Vec<_>>(), exp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_iters_extend() {
+        let a = CompressedSequence::from_iterator(1, 0..100_000);
+        let b = CompressedSequence::from_iterator(1, 50_000..150_000);
+
+        let mut out = CompressedSequence::new(1);
+        out.extend(a.intersect_iter(&b));
+        assert_eq!(out.to_vec(), (50_000..100_000).collect::<Vec<_>>());
+
+        let mut out = CompressedSequence::new(1);
+        out.extend(a.difference_iter(&b));
+        assert_eq!(out.to_vec(), (0..50_000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::cursor::Cursor;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Amount of positions sharing a single cache entry
+const BUCKET_SIZE: usize = 64;
+
+/// Default amount of shards of a [`SharedCache`]
+const DEFAULT_SHARDS: usize = 16;
+
+/// Thread safe cache with a bounded capacity to speed up positional lookups of multiple
+/// threads reading the same set. Positions are grouped into buckets which each remember the
+/// item holding the buckets first position. Buckets are distributed over independently locked
+/// shards and the least recently used bucket of a shard gets evicted if the shard is full.
+///
+/// A cache should only be used with a single set and has to be cleared after modifying it
+pub struct SharedCache {
+    shards: Vec<Mutex<Shard>>,
+    shard_capacity: usize,
+}
+
+/// Single shard of a shared cache
+#[derive(Default)]
+struct Shard {
+    /// Bucket => (cursor, last usage)
+    entries: HashMap<usize, (Cursor, u64)>,
+    tick: u64,
+}
+
+impl SharedCache {
+    /// Creates a new shared cache holding up to `capacity` entries
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, DEFAULT_SHARDS)
+    }
+
+    /// Creates a new shared cache holding up to `capacity` entries distributed over `shards`
+    /// shards. More shards reduce lock contention between threads
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        let shards = shards.clamp(1, capacity.max(1));
+        Self {
+            shards: (0..shards).map(|_| Mutex::default()).collect(),
+            shard_capacity: (capacity / shards).max(1),
+        }
+    }
+
+    /// Returns the maximum amount of entries the cache can hold
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.shard_capacity * self.shards.len()
+    }
+
+    /// Returns the amount of entries in the cache
+    pub fn len(&self) -> usize {
+        (0..self.shards.len())
+            .map(|i| self.shard(i).entries.len())
+            .sum()
+    }
+
+    /// Returns `true` if there are no entries in the cache
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all entries from the cache
+    pub fn clear(&self) {
+        for i in 0..self.shards.len() {
+            self.shard(i).entries.clear();
+        }
+    }
+
+    /// Returns the first position of the bucket holding `pos`
+    #[inline]
+    pub(crate) fn bucket_start(pos: usize) -> usize {
+        pos - pos % BUCKET_SIZE
+    }
+
+    /// Returns the cursor of the bucket holding `pos`
+    pub(crate) fn get(&self, pos: usize) -> Option<Cursor> {
+        let bucket = pos / BUCKET_SIZE;
+        let mut shard = self.shard(bucket);
+        shard.tick += 1;
+        let tick = shard.tick;
+
+        let (cursor, last_used) = shard.entries.get_mut(&bucket)?;
+        *last_used = tick;
+        Some(*cursor)
+    }
+
+    /// Sets the cursor of the bucket holding `pos`
+    pub(crate) fn insert(&self, pos: usize, cursor: Cursor) {
+        let bucket = pos / BUCKET_SIZE;
+        let mut shard = self.shard(bucket);
+        shard.tick += 1;
+        let tick = shard.tick;
+
+        if shard.entries.len() >= self.shard_capacity && !shard.entries.contains_key(&bucket) {
+            let lru = shard
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(bucket, _)| *bucket);
+            if let Some(lru) = lru {
+                shard.entries.remove(&lru);
+            }
+        }
+
+        shard.entries.insert(bucket, (cursor, tick));
+    }
+
+    #[inline]
+    fn shard(&self, bucket: usize) -> MutexGuard<'_, Shard> {
+        // Entries are always valid so there is nothing to recover from a poisoned lock
+        self.shards[bucket % self.shards.len()]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for SharedCache {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS * 256)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SharedCache;
+    use crate::test_utils::sample_set;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_shared_cache() {
+        let (comp_seq, exp) = sample_set(10);
+        let cache = SharedCache::new(64);
+
+        for (pos, i) in exp.iter().enumerate().rev() {
+            assert_eq!(comp_seq.get_shared(pos, &cache), Some(*i));
+        }
+        assert_eq!(comp_seq.get_shared(exp.len(), &cache), None);
+        assert!(cache.len() <= cache.capacity());
+        assert!(!cache.is_empty());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_shared_cache_evicted() {
+        let (mut comp_seq, exp) = sample_set(10);
+        comp_seq.update_index(10.0);
+        let cache = SharedCache::new(16);
+        assert!(exp.len() / 64 > cache.capacity());
+
+        // Misses of evicted buckets start from the previous bucket or the index
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..10_000 {
+            let pos = rng.gen_range(0..exp.len());
+            assert_eq!(comp_seq.get_shared(pos, &cache), Some(exp[pos]));
+        }
+        assert!(cache.len() <= cache.capacity());
+    }
+
+    #[test]
+    fn test_shared_cache_threads() {
+        let (mut comp_seq, exp) = sample_set(10);
+        comp_seq.update_index(5.0);
+        let cache = SharedCache::with_shards(128, 4);
+
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let (comp_seq, exp, cache) = (&comp_seq, &exp, &cache);
+                s.spawn(move || {
+                    for pos in (t..exp.len()).step_by(3 + t) {
+                        assert_eq!(comp_seq.get_shared(pos, cache), Some(exp[pos]));
+                    }
+                });
+            }
+        });
+
+        assert!(cache.len() <= 128);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{cursor::Cursor, iter::CompSeqIterRef, CompressedSequence};
+use core::{
+    iter::Take,
+    ops::{Bound, RangeBounds},
+};
+
+/// Borrowed view over a positional range of a [`CompressedSequence`].
+/// Created by [`CompressedSequence::slice`]
+#[derive(Debug, Clone, Copy)]
+pub struct CompressedSequenceSlice<'a> {
+    set: &'a CompressedSequence,
+    /// Position of the item holding the first value
+    vec_pos: usize,
+    /// Position of the first value within its item
+    ipos: usize,
+    /// Position of the first value within the set
+    start: usize,
+    len: usize,
+}
+
+impl CompressedSequence {
+    /// Returns a view over the values at the positions within `range`. Both bounds get located
+    /// by a single cursor which jumps to the closest entry of the position index, so the items
+    /// in front of the range only get scanned if the set isn't indexed
+    ///
+    /// # Panics
+    /// panics if the start of the range is greater than its end or if the end is out of bounds
+    pub fn slice<R>(&self, range: R) -> CompressedSequenceSlice<'_>
+    where
+        R: RangeBounds<usize>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(s) => *s,
+            Bound::Excluded(s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let mut cursor = Cursor::new();
+        let first = cursor.seek(self, start);
+        let end = match range.end_bound() {
+            Bound::Included(e) => e + 1,
+            Bound::Excluded(e) => *e,
+            Bound::Unbounded => cursor.seek_end(self),
+        };
+
+        assert!(start <= end, "slice starts at {start} but ends at {end}");
+        if !matches!(range.end_bound(), Bound::Unbounded) {
+            assert!(
+                end == 0 || cursor.seek(self, end - 1).is_some(),
+                "slice end {end} out of range"
+            );
+        }
+
+        let (vec_pos, i_len) = first.unwrap_or((self.seq.len(), start));
+        CompressedSequenceSlice {
+            set: self,
+            vec_pos,
+            ipos: start - i_len,
+            start,
+            len: end - start,
+        }
+    }
+}
+
+impl<'a> CompressedSequenceSlice<'a> {
+    /// Gets the value at the given position within the slice
+    pub fn get(&self, pos: usize) -> Option<u32> {
+        if pos >= self.len {
+            return None;
+        }
+
+        let pos = self.start + pos;
+        let mut cursor = Cursor::at_item(self.vec_pos, self.start - self.ipos);
+        let (vec_pos, i_len) = cursor.seek(self.set, pos)?;
+        self.set.seq[vec_pos].at(pos - i_len, self.set.step)
+    }
+
+    /// Returns an iterator over all values of the slice
+    #[inline]
+    pub fn iter(&self) -> Take<CompSeqIterRef<'a>> {
+        let (seq, step) = (&self.set.seq, self.set.step);
+        CompSeqIterRef::from_position(seq, step, self.vec_pos, self.ipos).take(self.len)
+    }
+
+    /// Returns the amount of values in the slice
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there is no value in the slice
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copies the values of the slice into a new set. Items get copied as a whole and only
+    /// the items at the bounds of the slice get cut
+    pub fn to_owned(&self) -> CompressedSequence {
+        let step = self.set.step;
+        let mut out = CompressedSequence::new(step);
+        out.index_strategy = self.set.index_strategy;
+        out.index_stride = self.set.index_stride;
+
+        let mut remaining = self.len;
+        let mut ipos = self.ipos;
+        for item in &self.set.seq[self.vec_pos..] {
+            if remaining == 0 {
+                break;
+            }
+
+            let mut item = *item;
+            if ipos > 0 {
+                item = item.split(ipos, step).1;
+                ipos = 0;
+            }
+            if item.len() > remaining {
+                item = item.split(remaining, step).0;
+            }
+
+            remaining -= item.len();
+            out.seq.push(item);
+        }
+
+        out.extend_index(0);
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_utils::sample_set, CompressedSequence};
+
+    #[test]
+    fn test_slice() {
+        let (mut comp_seq, exp) = sample_set(10);
+        comp_seq.update_index(20.0);
+        let len = exp.len();
+
+        for (start, end) in [
+            (0, 0),
+            (0, 1),
+            (0, len),
+            (1, 2),
+            (100, 200),
+            (150, 1500),
+            (len - 3, len),
+            (len, len),
+        ] {
+            let slice = comp_seq.slice(start..end);
+            let exp = &exp[start..end];
+
+            assert_eq!(slice.len(), exp.len());
+            assert_eq!(slice.is_empty(), exp.is_empty());
+            assert_eq!(slice.iter().collect::<Vec<_>>(), exp);
+            for (pos, i) in exp.iter().enumerate() {
+                assert_eq!(slice.get(pos), Some(*i));
+            }
+            assert_eq!(slice.get(exp.len()), None);
+
+            let owned = slice.to_owned();
+            assert_eq!(owned.to_vec(), exp);
+            assert_eq!(owned.get(exp.len().saturating_sub(1)), exp.last().copied());
+        }
+
+        assert_eq!(comp_seq.slice(..).len(), len);
+        for start in [0, 1, 500, len - 1, len] {
+            let slice = comp_seq.slice(start..);
+            assert_eq!(slice.len(), len - start);
+            assert_eq!(slice.iter().collect::<Vec<_>>(), exp[start..]);
+        }
+        assert!(CompressedSequence::new(10).slice(..).is_empty());
+        assert_eq!(
+            comp_seq.slice(10..=20).iter().collect::<Vec<_>>(),
+            exp[10..=20]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_out_of_range() {
+        let (comp_seq, exp) = sample_set(10);
+        comp_seq.slice(0..exp.len() + 1);
+    }
+}
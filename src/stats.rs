@@ -0,0 +1,117 @@
+use crate::{run::Run, CompressedSequence};
+use alloc::vec::Vec;
+
+impl CompressedSequence {
+    /// Returns the smallest value of the set. Named differently than [`Ord::min`] which
+    /// compares two sets
+    #[inline]
+    pub fn min_value(&self) -> Option<u32> {
+        self.first_value()
+    }
+
+    /// Returns the largest value of the set
+    #[inline]
+    pub fn max_value(&self) -> Option<u32> {
+        Some(self.last_item()?.last_number(self.step))
+    }
+
+    /// Returns the sum of all values. Sequence items are summed up in closed form
+    pub fn sum(&self) -> u64 {
+        self.seq
+            .iter()
+            .map(|item| {
+                let run = Run::from_item(item, self.step);
+                let len = run.len as u64;
+                len * run.start as u64 + run.step as u64 * (len * (len - 1) / 2)
+            })
+            .sum()
+    }
+
+    /// Returns the arithmetic mean of all values or `None` if the set is empty
+    pub fn mean(&self) -> Option<f64> {
+        let len = self.len();
+        (len > 0).then(|| self.sum() as f64 / len as f64)
+    }
+
+    /// Returns the amount of values within each bucket of `bucket_size` successive numbers as
+    /// `(first number of the bucket, count)`. Buckets start at multiples of `bucket_size` and
+    /// empty buckets are left out
+    ///
+    /// # Panics
+    /// panics if `bucket_size` is zero
+    pub fn histogram(&self, bucket_size: u32) -> Vec<(u32, usize)> {
+        assert!(bucket_size > 0, "Bucket size must not be zero");
+        let size = bucket_size as u64;
+
+        let mut buckets: Vec<(u32, usize)> = Vec::new();
+        for item in self.seq.iter() {
+            let mut run = Run::from_item(item, self.step);
+            while !run.is_empty() {
+                let bucket = run.start as u64 / size * size;
+                // Values of the run within the bucket
+                let count = run.lower_bound(bucket + size);
+
+                match buckets.last_mut() {
+                    Some((start, c)) if *start as u64 == bucket => *c += count as usize,
+                    _ => buckets.push((bucket as u32, count as usize)),
+                }
+                run = run.skip(count);
+            }
+        }
+
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::sample_values;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_stats() {
+        let values = sample_values();
+        for step in [1, 3, 5, 10] {
+            let set = CompressedSequence::from_iterator(step, values.iter().copied());
+            let sum: u64 = values.iter().map(|i| *i as u64).sum();
+
+            assert_eq!(set.min_value(), Some(0));
+            assert_eq!(set.max_value(), Some(u32::MAX));
+            assert_eq!(set.sum(), sum);
+            assert_eq!(set.mean(), Some(sum as f64 / values.len() as f64));
+        }
+
+        let empty = CompressedSequence::new(1);
+        assert_eq!(empty.min_value(), None);
+        assert_eq!(empty.max_value(), None);
+        assert_eq!(empty.sum(), 0);
+        assert_eq!(empty.mean(), None);
+        assert!(empty.histogram(10).is_empty());
+    }
+
+    #[test]
+    fn test_sum_full_range() {
+        let set = CompressedSequence::from_iterator(1, [0, u32::MAX]);
+        assert_eq!(set.sum(), u32::MAX as u64);
+
+        let set = CompressedSequence::from_iterator(1 << 16, (0..=u32::MAX).step_by(1 << 16));
+        let exp: u64 = (0..=u32::MAX).step_by(1 << 16).map(|i| i as u64).sum();
+        assert_eq!(set.sum(), exp);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let values = sample_values();
+        let set = CompressedSequence::from_iterator(3, values.iter().copied());
+
+        for bucket_size in [1, 7, 1000, 65_536, u32::MAX] {
+            let mut exp = BTreeMap::new();
+            for v in values.iter() {
+                *exp.entry(v / bucket_size * bucket_size).or_insert(0) += 1;
+            }
+            let exp: Vec<(u32, usize)> = exp.into_iter().collect();
+            assert_eq!(set.histogram(bucket_size), exp);
+        }
+    }
+}
@@ -0,0 +1,471 @@
+#[cfg(feature = "serde")]
+use crate::codec::{self, DecodeError};
+use crate::{item::Item, iter::CompSeqIterRef, CompressedSequence};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+/// Container for many sequences identified by a key. The items of all sequences are packed
+/// into a single arena and each key only stores the range of its items. This avoids the
+/// overhead of a separate [`CompressedSequence`] per key and serializes as a single blob
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        bound(
+            serialize = "K: Serialize + Eq + Hash",
+            deserialize = "K: Deserialize<'de> + Eq + Hash"
+        ),
+        try_from = "SerializedStore<K>"
+    )
+)]
+pub struct SequenceStore<K> {
+    items: Vec<Item>,
+    entries: HashMap<K, Entry>,
+    /// Amount of items in the arena which don't belong to a sequence anymore
+    #[cfg_attr(feature = "serde", serde(skip))]
+    unused: usize,
+}
+
+/// Serialized fields of a [`SequenceStore`], which get validated before creating the store
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(
+    rename = "SequenceStore",
+    bound(deserialize = "K: Deserialize<'de> + Eq + Hash")
+)]
+struct SerializedStore<K> {
+    items: Vec<Item>,
+    entries: HashMap<K, Entry>,
+}
+
+/// Location of a sequence within the item arena
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Entry {
+    step: u32,
+    start: u32,
+    end: u32,
+}
+
+/// Borrowed sequence within a [`SequenceStore`]
+#[derive(Debug, Clone, Copy)]
+pub struct StoredSequence<'a> {
+    items: &'a [Item],
+    step: u32,
+}
+
+impl<K> SequenceStore<K>
+where
+    K: Eq + Hash,
+{
+    /// Creates a new empty store
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            items: vec![],
+            entries: HashMap::new(),
+            unused: 0,
+        }
+    }
+
+    /// Inserts a sequence for the given key and returns the sequence previously stored for it.
+    /// The items get appended to the arena while the items of a replaced sequence stay unused
+    /// until the arena gets compacted. This happens once more than half of the arena is
+    /// unused, which makes inserting amortized linear in the size of the sequence
+    ///
+    /// # Panics
+    /// panics if the arena would exceed `u32::MAX` items
+    pub fn insert(&mut self, key: K, seq: CompressedSequence) -> Option<CompressedSequence> {
+        let start = arena_offset(self.items.len());
+        let end = arena_offset(self.items.len() + seq.seq.len());
+        self.items.extend_from_slice(&seq.seq);
+
+        let entry = Entry {
+            step: seq.step,
+            start,
+            end,
+        };
+        let old = self.entries.insert(key, entry)?;
+        Some(self.release(old))
+    }
+
+    /// Removes the sequence of the given key from the store. Its items stay in the arena like
+    /// the items of a replaced sequence in [`insert`](SequenceStore::insert)
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<CompressedSequence>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.entries.remove(key)?;
+        Some(self.release(entry))
+    }
+
+    /// Returns the sequence stored for the given key
+    pub fn get<Q>(&self, key: &Q) -> Option<StoredSequence<'_>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.entries.get(key)?;
+        Some(StoredSequence {
+            items: &self.items[entry.start as usize..entry.end as usize],
+            step: entry.step,
+        })
+    }
+
+    /// Returns an iterator over all values of the sequence stored for the given key
+    #[inline]
+    pub fn iter<Q>(&self, key: &Q) -> Option<CompSeqIterRef<'_>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Some(self.get(key)?.iter())
+    }
+
+    /// Returns `true` if the sequence of the given key contains `value`
+    #[inline]
+    pub fn contains<Q>(&self, key: &Q, value: u32) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some_and(|i| i.contains(value))
+    }
+
+    /// Returns `true` if there is a sequence stored for the given key
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns an iterator over all keys in the store
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.keys()
+    }
+
+    /// Returns the amount of sequences in the store
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there is no sequence in the store
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Shrinks the capacity of the item arena and the key table as much as possible
+    pub fn shrink_to_fit(&mut self) {
+        self.compact();
+        self.items.shrink_to_fit();
+        self.entries.shrink_to_fit();
+    }
+}
+
+impl<K> SequenceStore<K> {
+    /// Copies the items of a sequence which got removed from the entries and marks them unused
+    fn release(&mut self, entry: Entry) -> CompressedSequence {
+        let mut seq = CompressedSequence::new(entry.step);
+        seq.seq = self.items[entry.start as usize..entry.end as usize].to_vec();
+
+        self.unused += seq.seq.len();
+        if self.unused * 2 > self.items.len() {
+            self.compact();
+        }
+
+        seq
+    }
+
+    /// Moves the items of all sequences to the front of the arena and drops unused items
+    fn compact(&mut self) {
+        let mut entries: Vec<&mut Entry> = self.entries.values_mut().collect();
+        entries.sort_unstable_by_key(|i| i.start);
+
+        let mut len = 0;
+        for entry in entries {
+            let range = entry.start as usize..entry.end as usize;
+            let start = len;
+            len += range.len();
+            self.items.copy_within(range, start);
+            // Offsets only get smaller
+            entry.start = start as u32;
+            entry.end = len as u32;
+        }
+
+        self.items.truncate(len);
+        self.unused = 0;
+    }
+}
+
+/// Converts a position within the arena into an offset
+///
+/// # Panics
+/// panics if the position exceeds `u32::MAX`
+#[inline]
+fn arena_offset(pos: usize) -> u32 {
+    u32::try_from(pos).expect("Store exceeds u32::MAX items")
+}
+
+#[cfg(feature = "serde")]
+impl<K> TryFrom<SerializedStore<K>> for SequenceStore<K> {
+    type Error = DecodeError;
+
+    fn try_from(store: SerializedStore<K>) -> Result<Self, Self::Error> {
+        let SerializedStore { items, entries } = store;
+
+        let mut ranges: Vec<_> = entries.values().map(|i| (i.start, i.end)).collect();
+        ranges.sort_unstable();
+        // Ranges of different sequences must not overlap
+        let mut end = 0;
+        for (start, next_end) in ranges {
+            if start < end || start > next_end {
+                return Err(DecodeError::Overflow);
+            }
+            end = next_end;
+        }
+        if end as usize > items.len() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let mut used = 0;
+        for entry in entries.values() {
+            let range = entry.start as usize..entry.end as usize;
+            used += range.len();
+            codec::check_items(&items[range], entry.step)?;
+        }
+
+        Ok(Self {
+            unused: items.len() - used,
+            items,
+            entries,
+        })
+    }
+}
+
+impl<K> Default for SequenceStore<K>
+where
+    K: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> FromIterator<(K, CompressedSequence)> for SequenceStore<K>
+where
+    K: Eq + Hash,
+{
+    fn from_iter<T: IntoIterator<Item = (K, CompressedSequence)>>(iter: T) -> Self {
+        let mut store = Self::new();
+        for (key, seq) in iter {
+            store.insert(key, seq);
+        }
+        store
+    }
+}
+
+impl<'a> StoredSequence<'a> {
+    /// Gets the value at the given position
+    pub fn get(&self, pos: usize) -> Option<u32> {
+        let mut i_len = 0;
+        for item in self.items {
+            let next_len = i_len + item.len();
+            if pos < next_len {
+                return item.at(pos - i_len, self.step);
+            }
+            i_len = next_len;
+        }
+        None
+    }
+
+    /// Returns `true` if the sequence contains `value` using binary search
+    pub fn contains(&self, value: u32) -> bool {
+        let pos = self
+            .items
+            .partition_point(|i| i.last_number(self.step) < value);
+        self.items
+            .get(pos)
+            .is_some_and(|i| i.contains(value, self.step))
+    }
+
+    /// Returns an iterator over all values of the sequence
+    #[inline]
+    pub fn iter(&self) -> CompSeqIterRef<'a> {
+        CompSeqIterRef::from_items(self.items, self.step)
+    }
+
+    /// Returns the amount of values in the sequence
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.iter().map(|i| i.len()).sum()
+    }
+
+    /// Returns `true` if there is no value in the sequence
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Copies the sequence into a new [`CompressedSequence`]
+    pub fn to_sequence(&self) -> CompressedSequence {
+        let mut seq = CompressedSequence::new(self.step);
+        seq.seq = self.items.to_vec();
+        seq
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SequenceStore;
+    use crate::CompressedSequence;
+
+    fn make_store() -> (SequenceStore<String>, Vec<(String, Vec<u32>)>) {
+        let mut exp = vec![];
+        for term in 0..50u32 {
+            let values: Vec<u32> = (term..term * 100)
+                .step_by(term as usize + 1)
+                .chain([term * 1000, term * 1000 + 7])
+                .collect();
+            exp.push((format!("term{term}"), values));
+        }
+
+        let store = exp
+            .iter()
+            .map(|(key, values)| {
+                let step = key.len() as u32;
+                let seq = CompressedSequence::from_iterator(step, values.iter().copied());
+                (key.clone(), seq)
+            })
+            .collect();
+
+        (store, exp)
+    }
+
+    fn check_store(store: &SequenceStore<String>, exp: &[(String, Vec<u32>)]) {
+        assert_eq!(store.len(), exp.len());
+
+        for (key, values) in exp {
+            let seq = store.get(key).unwrap();
+            assert_eq!(seq.len(), values.len());
+            assert_eq!(store.iter(key).unwrap().collect::<Vec<_>>(), *values);
+            assert_eq!(seq.to_sequence().to_vec(), *values);
+
+            for (pos, value) in values.iter().enumerate() {
+                assert_eq!(seq.get(pos), Some(*value));
+                assert!(store.contains(key, *value));
+                let next = value + 1;
+                assert_eq!(store.contains(key, next), values.contains(&next));
+            }
+            assert_eq!(seq.get(values.len()), None);
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let (mut store, mut exp) = make_store();
+        check_store(&store, &exp);
+        assert!(store.get("unknown").is_none());
+        assert!(!store.contains("unknown", 1));
+
+        let removed = store.remove("term10").unwrap();
+        let pos = exp.iter().position(|i| i.0 == "term10").unwrap();
+        assert_eq!(removed.to_vec(), exp.remove(pos).1);
+        check_store(&store, &exp);
+
+        let old = store.insert(
+            "term20".to_string(),
+            CompressedSequence::from_iterator(1, 0..10),
+        );
+        assert!(old.is_some());
+        let pos = exp.iter().position(|i| i.0 == "term20").unwrap();
+        exp[pos].1 = (0..10).collect();
+        check_store(&store, &exp);
+    }
+
+    #[test]
+    fn test_store_update() {
+        let (mut store, exp) = make_store();
+        let live: usize = exp
+            .iter()
+            .map(|(key, _)| store.get(key).unwrap().items.len())
+            .sum();
+
+        // Replaced items get dropped once they take up more than half of the arena
+        for i in 0..1000 {
+            let seq = CompressedSequence::from_iterator(7, (i..i + 700).step_by(7));
+            store.insert("term3".to_string(), seq);
+            assert!(store.items.len() <= 2 * (live + 100));
+        }
+
+        let mut exp = exp;
+        exp[3].1 = (999..999 + 700).step_by(7).collect();
+        check_store(&store, &exp);
+
+        store.shrink_to_fit();
+        assert_eq!(store.unused, 0);
+        check_store(&store, &exp);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_store_serialize() {
+        let (store, exp) = make_store();
+        let json = serde_json::to_string(&store).unwrap();
+        let store: SequenceStore<String> = serde_json::from_str(&json).unwrap();
+        check_store(&store, &exp);
+        // Only the arena and the entries get serialized
+        assert!(!json.contains("unused"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_store_deserialize_invalid() {
+        let (mut store, mut exp) = make_store();
+        let removed = store.remove("term20").unwrap();
+        exp.retain(|i| i.0 != "term20");
+        let unused = removed.seq().len();
+        assert_eq!(store.unused, unused);
+
+        let bytes = bincode::serialize(&store).unwrap();
+        let decoded: SequenceStore<String> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.unused, unused);
+        check_store(&decoded, &exp);
+
+        // Truncated blobs never succeed
+        for end in (0..bytes.len()).step_by(61) {
+            assert!(bincode::deserialize::<SequenceStore<String>>(&bytes[..end]).is_err());
+        }
+
+        let json = serde_json::to_string(&store).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let items = json["items"].as_array().unwrap().len() as u32;
+        for (key, field, value) in [
+            // Out of bounds
+            ("term3", "end", items + 1),
+            // Start after the end
+            ("term3", "start", items),
+            // Overlaps the next sequence
+            (
+                "term3",
+                "end",
+                json["entries"]["term4"]["start"].as_u64().unwrap() as u32 + 1,
+            ),
+            // Step size zero
+            ("term3", "step", 0),
+        ] {
+            let mut json = json.clone();
+            json["entries"][key][field] = value.into();
+            assert!(serde_json::from_value::<SequenceStore<String>>(json).is_err());
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{
+    codec::{self, DecodeError},
+    item::Item,
+};
+use std::io::{self, BufReader, Read, Write};
+
+/// Magic bytes every stream starts with
+pub const MAGIC: [u8; 4] = *b"CSST";
+
+/// Format version written by [`SequenceWriter`]
+pub const VERSION: u16 = 1;
+
+/// Size of the stream header in bytes
+pub const HEADER_SIZE: u64 = 10;
+
+/// Item header with the unused tag 3 which marks the end of the items
+const END_MARKER: u8 = 0b11;
+
+/// Entry of the sparse index, pointing to an item within the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IndexEntry {
+    /// First value of the item
+    pub value: u32,
+    /// Position of the first value within the set
+    pub position: u64,
+    /// Byte offset of the item within the stream
+    pub offset: u64,
+    /// Last value of the previous item, which the item is encoded relative to. Zero for the
+    /// first item
+    pub base: u32,
+}
+
+impl IndexEntry {
+    const SIZE: u64 = 24;
+
+    /// Returns the last value of the previous item or `None` for the first item
+    #[inline]
+    pub(crate) fn last(&self) -> Option<u32> {
+        (self.position > 0).then_some(self.base)
+    }
+}
+
+/// Counts and index stored at the end of a stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Trailer {
+    /// Amount of items
+    pub items: u64,
+    /// Amount of values
+    pub len: u64,
+    /// Sparse index, ordered by value
+    pub index: Vec<IndexEntry>,
+}
+
+impl Trailer {
+    fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.items.to_le_bytes())?;
+        w.write_all(&self.len.to_le_bytes())?;
+        w.write_all(&(self.index.len() as u64).to_le_bytes())?;
+        for entry in self.index.iter() {
+            w.write_all(&entry.value.to_le_bytes())?;
+            w.write_all(&entry.position.to_le_bytes())?;
+            w.write_all(&entry.offset.to_le_bytes())?;
+            w.write_all(&entry.base.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a trailer from `r`. With `with_index` being `false` the index entries aren't read
+    pub(crate) fn read<R: Read>(r: &mut R, with_index: bool) -> io::Result<Self> {
+        let items = read_u64(r)?;
+        let len = read_u64(r)?;
+        let index_len = read_u64(r)?;
+
+        let mut index = vec![];
+        if with_index {
+            // Don't trust the length for allocations
+            index.reserve(index_len.min(1 << 16) as usize);
+            for _ in 0..index_len {
+                index.push(IndexEntry {
+                    value: read_u32(r)?,
+                    position: read_u64(r)?,
+                    offset: read_u64(r)?,
+                    base: read_u32(r)?,
+                });
+            }
+        } else {
+            let skip = index_len.saturating_mul(IndexEntry::SIZE);
+            if io::copy(&mut r.take(skip), &mut io::sink())? != skip {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+
+        Ok(Self { items, len, index })
+    }
+}
+
+/// Writes a set value by value into `W`. Values are combined into items the same way
+/// [`CompressedSequence::push`](crate::CompressedSequence::push) does and every finished item
+/// gets written immediately. The stream is only complete after calling
+/// [`finish`](SequenceWriter::finish)
+///
+/// A stream starts with a header (magic, version and step size) followed by the items, each
+/// encoded like in [`CompressedSequence::to_bytes`](crate::CompressedSequence::to_bytes), and
+/// an end marker. The trailer holds the item and value counts as well as an optional sparse
+/// index. The last 8 bytes of a stream contain the offset of the trailer
+pub struct SequenceWriter<W: Write> {
+    writer: W,
+    step: u32,
+    /// Item that still accepts new values
+    current: Option<Item>,
+    /// Last value of the last written item
+    last: Option<u32>,
+    /// Bytes written so far
+    offset: u64,
+    index_stride: u64,
+    trailer: Trailer,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> SequenceWriter<W> {
+    /// Creates a new writer and writes the stream header
+    pub fn new(writer: W, step: u32) -> io::Result<Self> {
+        Self::with_index(writer, step, 0.0)
+    }
+
+    /// Creates a new writer which stores a sparse index in the trailer. The rate is a value
+    /// between 0% and 100% that indicates how much percent of items should be indexed
+    pub fn with_index(mut writer: W, step: u32, rate: f64) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&step.to_le_bytes())?;
+
+        let index_stride = if rate > 0.0 {
+            ((100.0 / rate) as u64).max(1)
+        } else {
+            0
+        };
+
+        Ok(Self {
+            writer,
+            step,
+            current: None,
+            last: None,
+            offset: HEADER_SIZE,
+            index_stride,
+            trailer: Trailer::default(),
+            buf: Vec::with_capacity(16),
+        })
+    }
+
+    /// Adds a value to the end of the stream
+    pub fn push(&mut self, value: u32) -> io::Result<()> {
+        let step = self.step;
+        let added = self
+            .current
+            .as_mut()
+            .is_some_and(|item| item.try_add(value, step));
+
+        if !added {
+            self.flush_item()?;
+            self.current = Some(Item::new(value));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the amount of values pushed so far
+    pub fn len(&self) -> u64 {
+        self.trailer.len + self.current.map_or(0, |i| i.len() as u64)
+    }
+
+    /// Returns `true` if no value was pushed yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the remaining item and the trailer and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_item()?;
+
+        self.writer.write_all(&[END_MARKER])?;
+        self.trailer.write(&mut self.writer)?;
+        self.writer.write_all(&(self.offset + 1).to_le_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn flush_item(&mut self) -> io::Result<()> {
+        let Some(item) = self.current.take() else {
+            return Ok(());
+        };
+
+        if self.index_stride > 0 && self.trailer.items.is_multiple_of(self.index_stride) {
+            self.trailer.index.push(IndexEntry {
+                value: item.first_number(),
+                position: self.trailer.len,
+                offset: self.offset,
+                base: self.last.unwrap_or(0),
+            });
+        }
+
+        self.buf.clear();
+        codec::encode_item(&mut self.buf, &item, self.last);
+        self.writer.write_all(&self.buf)?;
+
+        self.offset += self.buf.len() as u64;
+        self.last = Some(item.last_number(self.step));
+        self.trailer.items += 1;
+        self.trailer.len += item.len() as u64;
+        Ok(())
+    }
+}
+
+/// Lazily reads the values of a stream created by [`SequenceWriter`]
+pub struct SequenceReader<R: Read> {
+    reader: BufReader<R>,
+    step: u32,
+    item: Option<Item>,
+    ipos: usize,
+    /// Last value of the last read item
+    last: Option<u32>,
+    /// Bytes read so far
+    offset: u64,
+    items: u64,
+    len: u64,
+    done: bool,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> SequenceReader<R> {
+    /// Creates a new reader and reads the stream header
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let step = read_header(&mut reader)?;
+
+        Ok(Self {
+            reader,
+            step,
+            item: None,
+            ipos: 0,
+            last: None,
+            offset: HEADER_SIZE,
+            items: 0,
+            len: 0,
+            done: false,
+            buf: Vec::with_capacity(16),
+        })
+    }
+
+    /// Returns the step size of the stream
+    #[inline]
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    /// Reads the next item along with its location within the stream
+    pub(crate) fn next_item(&mut self) -> io::Result<Option<(Item, IndexEntry)>> {
+        let entry = IndexEntry {
+            value: 0,
+            position: self.len,
+            offset: self.offset,
+            base: self.last.unwrap_or(0),
+        };
+        let item = self.read_item()?;
+        Ok(item.map(|item| {
+            let value = item.first_number();
+            (item, IndexEntry { value, ..entry })
+        }))
+    }
+
+    /// Reads the next item. Returns `None` after the end marker and verifies the trailer
+    fn read_item(&mut self) -> io::Result<Option<Item>> {
+        self.buf.clear();
+        self.read_varint()?;
+        if self.buf == [END_MARKER] {
+            self.offset += 1;
+            let trailer = Trailer::read(&mut self.reader, false)?;
+            if trailer.items != self.items || trailer.len != self.len {
+                return Err(invalid_data(DecodeError::LengthMismatch));
+            }
+            return Ok(None);
+        }
+
+        // Items with a tag other than a single number have a second varint
+        if self.buf[0] & 0b11 != 0 {
+            self.read_varint()?;
+        }
+        self.offset += self.buf.len() as u64;
+
+        let item = codec::decode_item(&mut self.buf.as_slice(), self.last, self.step)
+            .map_err(invalid_data)?;
+        self.last = Some(item.last_number(self.step));
+        self.items += 1;
+        self.len += item.len() as u64;
+        Ok(Some(item))
+    }
+
+    /// Appends the bytes of a single varint to the buffer
+    fn read_varint(&mut self) -> io::Result<()> {
+        for _ in 0..10 {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            self.buf.push(byte[0]);
+            if byte[0] & 0x80 == 0 {
+                return Ok(());
+            }
+        }
+        Err(invalid_data(DecodeError::Overflow))
+    }
+}
+
+impl<R: Read> Iterator for SequenceReader<R> {
+    type Item = io::Result<u32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(val) = self.item.and_then(|i| i.at(self.ipos, self.step)) {
+                self.ipos += 1;
+                return Some(Ok(val));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            self.ipos = 0;
+            match self.read_item() {
+                Ok(item) => {
+                    self.done = item.is_none();
+                    self.item = item;
+                }
+                Err(err) => {
+                    self.done = true;
+                    self.item = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Reads the stream header and returns the step size
+pub(crate) fn read_header<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
+    }
+
+    let mut version = [0; 2];
+    r.read_exact(&mut version)?;
+    match u16::from_le_bytes(version) {
+        VERSION => codec::check_step(read_u32(r)?).map_err(invalid_data),
+        v => Err(invalid_data(DecodeError::UnsupportedVersion(v))),
+    }
+}
+
+#[inline]
+pub(crate) fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test_utils::sample_values, CompressedSequence};
+    use std::io::Cursor;
+
+    fn write(values: &[u32], step: u32, rate: f64) -> Vec<u8> {
+        let mut writer = SequenceWriter::with_index(vec![], step, rate).unwrap();
+        for v in values {
+            writer.push(*v).unwrap();
+        }
+        assert_eq!(writer.len(), values.len() as u64);
+        writer.finish().unwrap()
+    }
+
+    fn read_trailer(bytes: &[u8]) -> Trailer {
+        let offset = u64::from_le_bytes(bytes[bytes.len() - 8..].try_into().unwrap());
+        let mut reader = &bytes[offset as usize..bytes.len() - 8];
+        let trailer = Trailer::read(&mut reader, true).unwrap();
+        assert!(reader.is_empty());
+        trailer
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let values = sample_values();
+        for (step, rate) in [(10, 0.0), (3, 1.0), (1, 100.0)] {
+            let bytes = write(&values, step, rate);
+
+            let reader = SequenceReader::new(Cursor::new(&bytes)).unwrap();
+            assert_eq!(reader.step(), step);
+            let read: Vec<u32> = reader.map(|i| i.unwrap()).collect();
+            assert_eq!(read, values);
+
+            // Same items as an in memory set
+            let set = CompressedSequence::from_iterator(step, values.iter().copied());
+            let trailer = read_trailer(&bytes);
+            assert_eq!(trailer.items, set.seq().len() as u64);
+            assert_eq!(trailer.len, values.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_stream_empty() {
+        let bytes = SequenceWriter::new(vec![], 5).unwrap().finish().unwrap();
+        let mut reader = SequenceReader::new(bytes.as_slice()).unwrap();
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_stream_index() {
+        let values = sample_values();
+        let bytes = write(&values, 3, 10.0);
+        let trailer = read_trailer(&bytes);
+        assert_eq!(trailer.index.len() as u64, trailer.items.div_ceil(10));
+
+        // Every entry allows decoding from the middle of the stream
+        for entry in trailer.index {
+            assert_eq!(values[entry.position as usize], entry.value);
+            let mut reader = &bytes[entry.offset as usize..];
+            let item = codec::decode_item(&mut reader, entry.last(), 3).unwrap();
+            assert_eq!(item.first_number(), entry.value);
+        }
+    }
+
+    #[test]
+    fn test_stream_invalid() {
+        let bytes = write(&sample_values(), 3, 0.0);
+
+        // Truncated streams never succeed
+        for len in [0, 4, HEADER_SIZE as usize, 100, bytes.len() - 9] {
+            let result: io::Result<Vec<u32>> = match SequenceReader::new(&bytes[..len]) {
+                Ok(reader) => reader.collect(),
+                Err(err) => Err(err),
+            };
+            assert!(result.is_err());
+        }
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(SequenceReader::new(magic.as_slice()).is_err());
+
+        let mut count = bytes.clone();
+        let offset = u64::from_le_bytes(bytes[bytes.len() - 8..].try_into().unwrap()) as usize;
+        count[offset + 8] ^= 1;
+        let result: io::Result<Vec<u32>> = SequenceReader::new(count.as_slice()).unwrap().collect();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::CompressedSequence;
+
+/// Values used by the tests of several modules: runs with the step sizes 10, 3 and 77, some
+/// outliers and a run with the step size 5 ending at `u32::MAX`
+pub(crate) fn sample_values() -> Vec<u32> {
+    let mut values: Vec<u32> = (0..10_000).step_by(10).collect();
+    values.extend([10_001, 10_005, 10_006, 80_000, 200_000]);
+    values.extend((300_000..330_000).step_by(3));
+    values.extend((400_000..500_000).step_by(77));
+    values.extend((u32::MAX - 1_000..=u32::MAX).step_by(5));
+    values
+}
+
+/// Set of the [`sample_values`] with the given step size along with the values
+pub(crate) fn sample_set(step: u32) -> (CompressedSequence, Vec<u32>) {
+    let values = sample_values();
+    (
+        CompressedSequence::from_iterator(step, values.iter().copied()),
+        values,
+    )
+}
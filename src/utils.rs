@@ -1,13 +1,10 @@
+use crate::CompressedSequence;
 use std::collections::HashMap;
 
-/// Returns the most frequent step size in the given set
+/// Returns the most frequent step size in the given set. The smallest step size wins ties
 #[inline]
 pub fn max_step_size(list: &[u32]) -> u32 {
-    get_steps_freq(list)
-        .iter()
-        .max_by(|a, b| a.1.cmp(&b.1))
-        .map(|i| *i.0)
-        .unwrap_or(1)
+    crate::most_common_step(list)
 }
 
 /// Returns the most frequent step sizes in the given set
@@ -18,3 +15,139 @@ pub fn get_steps_freq(list: &[u32]) -> HashMap<u32, u32> {
     }
     step_size_freq
 }
+
+/// Default amount of step sizes simulated by [`analyze`]
+const DEFAULT_CANDIDATES: usize = 5;
+
+/// Projected compression of a set using a specific step size
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepCandidate {
+    /// Simulated step size
+    pub step: u32,
+    /// Amount of successive values having this step size
+    pub frequency: u32,
+    /// Amount of number items
+    pub number_items: usize,
+    /// Amount of sequence items
+    pub sequence_items: usize,
+    /// Bytes used by all items
+    pub size_bytes: usize,
+    /// Amount of values stored in sequence items
+    pub sequence_values: usize,
+    /// Amount of values stored in number items
+    pub outlier_values: usize,
+}
+
+impl StepCandidate {
+    /// Returns the total amount of items
+    #[inline]
+    pub fn items(&self) -> usize {
+        self.number_items + self.sequence_items
+    }
+
+    /// Returns the share of values covered by sequences as value between 0.0 and 1.0
+    pub fn sequence_share(&self) -> f64 {
+        let len = self.sequence_values + self.outlier_values;
+        if len == 0 {
+            return 0.0;
+        }
+        self.sequence_values as f64 / len as f64
+    }
+}
+
+/// Report about how well a list of numbers compresses with different step sizes
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionReport {
+    /// Amount of distinct values analyzed
+    pub len: usize,
+    /// Bytes used by the values in a plain `Vec<u32>`
+    pub raw_bytes: usize,
+    /// Simulated step sizes, smallest projected size first
+    pub candidates: Vec<StepCandidate>,
+    /// Step size resulting in the smallest set
+    pub recommended_step: u32,
+}
+
+/// Simulates compressing the list with the most frequent step sizes and returns a report
+/// about the projected sizes
+#[inline]
+pub fn analyze(list: &[u32]) -> CompressionReport {
+    analyze_top(list, DEFAULT_CANDIDATES)
+}
+
+/// Simulates compressing the list with the `k` most frequent step sizes and returns a report
+/// about the projected sizes. The list doesn't need to be sorted
+pub fn analyze_top(list: &[u32], k: usize) -> CompressionReport {
+    let mut values = list.to_vec();
+    values.sort_unstable();
+    values.dedup();
+
+    let mut steps: Vec<_> = get_steps_freq(&values).into_iter().collect();
+    steps.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    steps.truncate(k);
+
+    let mut candidates: Vec<_> = steps
+        .into_iter()
+        .map(|(step, frequency)| simulate(&values, step, frequency))
+        .collect();
+    candidates.sort_by(|a, b| a.size_bytes.cmp(&b.size_bytes).then(a.step.cmp(&b.step)));
+
+    CompressionReport {
+        len: values.len(),
+        raw_bytes: values.len() * std::mem::size_of::<u32>(),
+        recommended_step: candidates.first().map(|i| i.step).unwrap_or(1),
+        candidates,
+    }
+}
+
+/// Builds a set from sorted and deduplicated `values` with the given step size
+fn simulate(values: &[u32], step: u32, frequency: u32) -> StepCandidate {
+    let mut seq = CompressedSequence::new(step);
+    seq.extend(values.iter().copied());
+    let stats = seq.memory_stats();
+
+    let outlier_values = stats.number_items * 2 - stats.half_number_items;
+    StepCandidate {
+        step,
+        frequency,
+        number_items: stats.number_items,
+        sequence_items: stats.sequence_items,
+        size_bytes: stats.heap_bytes,
+        sequence_values: stats.len - outlier_values,
+        outlier_values,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let mut list: Vec<u32> = (0..10_000).step_by(7).collect();
+        list.extend((20_000..30_000).step_by(3));
+        list.extend([10_001, 10_005, 15_000, 15_000]);
+        list.reverse();
+
+        let report = analyze(&list);
+        assert_eq!(report.len, list.len() - 1);
+        assert_eq!(report.raw_bytes, report.len * 4);
+        assert_eq!(report.recommended_step, 3);
+        assert!(report.candidates.len() <= DEFAULT_CANDIDATES);
+        assert_eq!(report.candidates[0].step, 3);
+
+        let step_7 = report.candidates.iter().find(|i| i.step == 7).unwrap();
+        assert_eq!(step_7.sequence_items, 1);
+        assert_eq!(step_7.sequence_values, 1429);
+        assert_eq!(step_7.sequence_values + step_7.outlier_values, report.len);
+        assert!(step_7.sequence_share() < report.candidates[0].sequence_share());
+    }
+
+    #[test]
+    fn test_analyze_empty() {
+        let report = analyze(&[]);
+        assert_eq!(report.len, 0);
+        assert!(report.candidates.is_empty());
+        assert_eq!(report.recommended_step, 1);
+    }
+}